@@ -17,7 +17,18 @@ type StatementDTO = {
 type ServerGameState = {
   statements: StatementDTO[];
   root: IndexDTO;
+  revision: number;
 };
+// the statements that changed from revision `base` to `revision`, see `GameDiff` in the README of the server.
+type ServerGameDiff = {
+  base: number;
+  revision: number;
+  statements: StatementDTO[];
+  removed: IndexDTO[];
+};
+// version of the websocket messages this client speaks, see `PROTOCOL_VERSION` of the server.
+const PROTOCOL_VERSION = 1;
+
 type IndexDTO = [number, number];
 type LinkDTO = { child: IndexDTO; parent: IndexDTO };
 type ServerError =
@@ -29,15 +40,15 @@ type ServerError =
 type ServerMessage =
   | NewNodeIdDTO
   | GameStateDTO
+  | GameDiffDTO
   | CommentDTO
   | AICooldownDTO
   | ErrorDTO
   | "Win";
 
 type NewNodeIdDTO = { NewNodeId: { id: IndexDTO } };
-type GameStateDTO = {
-  GameState: { statements: StatementDTO[]; root: IndexDTO };
-};
+type GameStateDTO = { GameState: ServerGameState };
+type GameDiffDTO = { GameDiff: ServerGameDiff };
 type CommentDTO = {
   Comment: { id: IndexDTO; comment: string; success: boolean };
 };
@@ -45,6 +56,9 @@ type AICooldownDTO = { AICooldown: { seconds: number } };
 type ErrorDTO = { Error: ServerError };
 
 type ClientMessage =
+  | { Hello: { protocol: number } }
+  | "GetGameState"
+  | { GetChanges: { since: number } }
   | { Add: { statement: string } }
   | { Delete: { id: IndexDTO } }
   | { Edit: { id: IndexDTO; statement: string } }
@@ -60,6 +74,21 @@ function toIndexDTO(index: Index): IndexDTO {
   let [i, gen] = index.split(",");
   return [parseInt(i), parseInt(gen)] as IndexDTO;
 }
+function applyDiff(
+  state: ServerGameState,
+  diff: ServerGameDiff
+): ServerGameState {
+  const replaced = new Set(
+    diff.statements.map((s) => s.id).concat(diff.removed).map(toIndex)
+  );
+  return {
+    ...state,
+    statements: state.statements
+      .filter((s) => !replaced.has(toIndex(s.id)))
+      .concat(diff.statements),
+    revision: diff.revision,
+  };
+}
 function toGraphData(state: ServerGameState): GraphData {
   let clientStateNodes: Node[] = [];
   let clientStateLinks: Link[] = [];
@@ -90,6 +119,9 @@ const Game = ({ root_statement }: { root_statement: string }) => {
     {
       onOpen: () => {
         console.log("WebSocket connection opened.");
+        sendJsonMessage({ Hello: { protocol: PROTOCOL_VERSION } });
+        // the server only broadcasts changes, the tree has to be asked for once.
+        sendJsonMessage("GetGameState");
         let setCorrectRoot: ClientMessage = {
           Edit: { id: [0, 0], statement: root_statement },
         };
@@ -106,11 +138,16 @@ const Game = ({ root_statement }: { root_statement: string }) => {
     [ReadyState.UNINSTANTIATED]: "Uninstantiated",
   }[readyState];
 
+  const [gameState, setGameState] = useState<ServerGameState | null>(null);
   const [graphData, setGraphData] = useState<GraphData>({
     nodes: [],
     links: [],
     rootId: "",
   });
+  const showGameState = (state: ServerGameState) => {
+    setGameState(state);
+    setGraphData(toGraphData(state));
+  };
   const send_message = (expression: ClientMessage) => {
     if (readyState == ReadyState.OPEN) {
       console.log("sending: " + expression);
@@ -138,8 +175,23 @@ const Game = ({ root_statement }: { root_statement: string }) => {
           break;
         }
         case "GameState" in message: {
-          let state = (message as GameStateDTO).GameState;
-          setGraphData(toGraphData(state));
+          showGameState((message as GameStateDTO).GameState);
+          break;
+        }
+        case "GameDiff" in message: {
+          let diff = (message as GameDiffDTO).GameDiff;
+          if (!gameState) {
+            // the tree did not arrive yet, its reply will contain this change.
+            break;
+          }
+          if (diff.revision <= gameState.revision) {
+            // known already.
+          } else if (diff.base <= gameState.revision) {
+            // a diff repeats every statement changed since `base`, so it also applies to later revisions.
+            showGameState(applyDiff(gameState, diff));
+          } else {
+            send_message({ GetChanges: { since: gameState.revision } });
+          }
           break;
         }
         case "Comment" in message: {
@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use super::normalize;
+
+/// identifies a question to a model for the verdict cache, ignoring case, whitespace and the order of premises.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct CacheKey {
+    model: String,
+    /// `None` for a statement that is checked by itself.
+    premises: Option<Vec<String>>,
+    conclusion: String,
+    /// only set for attacks, which argue against the conclusion or its inference from the premise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attacker: Option<String>,
+}
+
+impl CacheKey {
+    pub fn statement(model: String, statement: &str) -> Self {
+        Self {
+            model,
+            premises: None,
+            conclusion: normalize(statement),
+            attacker: None,
+        }
+    }
+
+    pub fn implication(model: String, premises: &[&str], conclusion: &str) -> Self {
+        let mut premises: Vec<String> = premises.iter().map(|premise| normalize(premise)).collect();
+        premises.sort();
+        premises.dedup();
+        Self {
+            model,
+            premises: Some(premises),
+            conclusion: normalize(conclusion),
+            attacker: None,
+        }
+    }
+
+    pub fn attack(model: String, attacker: &str, target: &str, premise: Option<&str>) -> Self {
+        Self {
+            model,
+            premises: premise.map(|premise| vec![normalize(premise)]),
+            conclusion: normalize(target),
+            attacker: Some(normalize(attacker)),
+        }
+    }
+}
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_case_whitespace_and_premise_order() {
+        let a = CacheKey::implication(
+            "m".into(),
+            &["Socrates is a man.", "Every man is mortal."],
+            "Socrates is mortal.",
+        );
+        let b = CacheKey::implication(
+            "m".into(),
+            &["every  man is mortal. ", "socrates is a man.", "Socrates is a man."],
+            " socrates is Mortal.",
+        );
+        assert_eq!(a.to_string(), b.to_string());
+    }
+
+    #[test]
+    fn distinguishes_models_and_questions() {
+        let statement = CacheKey::statement("m".into(), "Socrates is mortal.");
+        assert_ne!(statement, CacheKey::statement("n".into(), "Socrates is mortal."));
+        assert_ne!(statement, CacheKey::implication("m".into(), &[], "Socrates is mortal."));
+        assert_ne!(
+            CacheKey::implication("m".into(), &["Socrates is a god."], "Socrates is mortal."),
+            CacheKey::attack("m".into(), "Socrates is a god.", "Socrates is mortal.", None)
+        );
+    }
+}
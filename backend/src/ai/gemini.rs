@@ -0,0 +1,279 @@
+use std::fmt::Display;
+
+use reqwest::{self};
+use serde::{Deserialize, Serialize};
+
+use super::AiApi;
+use crate::EndpointConfig;
+
+const DEFAULT_ENDPOINT: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "gemini-pro";
+
+/// client for the Google Gemini `generateContent` api.
+pub struct Gemini {
+    model: String,
+    token: String,
+    endpoint: String,
+    rate_limit: u32,
+}
+
+impl Gemini {
+    pub fn new(config: &EndpointConfig) -> Self {
+        let endpoint = match config.endpoint.as_str() {
+            "" => DEFAULT_ENDPOINT,
+            endpoint => endpoint.trim_end_matches('/'),
+        };
+        Self {
+            model: config.model.clone().unwrap_or(DEFAULT_MODEL.into()),
+            token: config.token.clone(),
+            endpoint: endpoint.into(),
+            rate_limit: config.rate_limit.unwrap_or(0),
+        }
+    }
+
+    fn url(&self) -> String { format!("{}/models/{}:generateContent", self.endpoint, self.model) }
+}
+
+pub enum GeminiError {
+    NoCandidates,
+    Blocked(String),
+    ReqwestError(reqwest::Error),
+}
+
+impl From<reqwest::Error> for GeminiError {
+    fn from(error: reqwest::Error) -> Self { Self::ReqwestError(error) }
+}
+
+impl Display for GeminiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeminiError::NoCandidates => write!(f, "The AI did not answer."),
+            GeminiError::Blocked(reason) => write!(f, "The AI refused to answer ({}).", reason),
+            GeminiError::ReqwestError(e) => write!(f, "Could not reach the AI: {}", e),
+        }
+    }
+}
+
+impl AiApi for Gemini {
+    type AiError = GeminiError;
+
+    fn rate_limit(&self) -> u32 { self.rate_limit }
+    fn model(&self) -> String { self.model.clone() }
+
+    async fn request(&self, system_message: &str, input: &str) -> Result<String, Self::AiError> {
+        let request = GeminiRequest {
+            system_instruction: Content::new(None, system_message),
+            contents: vec![Content::new(Some(Role::User), input)],
+            generation_config: GenerationConfig {
+                response_mime_type: "application/json".into(),
+            },
+        };
+
+        let response = reqwest::Client::new()
+            .post(self.url())
+            .header("x-goog-api-key", &self.token)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<GeminiResponse>()
+            .await?;
+
+        if let Some(reason) = response.prompt_feedback.and_then(|feedback| feedback.block_reason) {
+            return Err(GeminiError::Blocked(reason));
+        }
+        let candidate = response
+            .candidates
+            .into_iter()
+            .next()
+            .ok_or(GeminiError::NoCandidates)?;
+        let text: String = candidate
+            .content
+            .ok_or(GeminiError::Blocked(candidate.finish_reason.unwrap_or_default()))?
+            .parts
+            .into_iter()
+            .map(|part| part.text)
+            .collect();
+        Ok(text)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    system_instruction: Content,
+    contents: Vec<Content>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    response_mime_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Role {
+    User,
+    Model,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Content {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<Role>,
+    parts: Vec<Part>,
+}
+
+impl Content {
+    fn new(role: Option<Role>, text: impl Into<String>) -> Self {
+        Self {
+            role,
+            parts: vec![Part { text: text.into() }],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Part {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<Content>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// what the mock server saw of the last request.
+    #[derive(Default)]
+    struct Received {
+        path: String,
+        token: Option<String>,
+        body: Value,
+    }
+
+    /// serve `response` with `status` on a random local port, return the endpoint and the received request.
+    async fn mock_server(status: StatusCode, response: Value) -> (String, Arc<Mutex<Received>>) {
+        let received = Arc::new(Mutex::new(Received::default()));
+        let app = Router::new()
+            .route(
+                "/v1beta/models/:method",
+                post(
+                    move |State(received): State<Arc<Mutex<Received>>>,
+                          Path(method): Path<String>,
+                          headers: HeaderMap,
+                          Json(body): Json<Value>| async move {
+                        *received.lock().unwrap() = Received {
+                            path: method,
+                            token: headers
+                                .get("x-goog-api-key")
+                                .map(|token| token.to_str().unwrap().to_string()),
+                            body,
+                        };
+                        (status, Json(response))
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/v1beta", address), received)
+    }
+
+    fn config(endpoint: String) -> EndpointConfig {
+        EndpointConfig {
+            disabled: false,
+            token: "secret".into(),
+            endpoint,
+            model: Some("gemini-test".into()),
+            rate_limit: Some(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn sends_generate_content_request() {
+        let (endpoint, received) = mock_server(
+            StatusCode::OK,
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "[TRUE] "}, {"text": "yes"}]}}]}),
+        )
+        .await;
+        let gemini = Gemini::new(&config(endpoint));
+
+        let answer = gemini.request("system", "statement").await.ok().unwrap();
+
+        assert_eq!(answer, "[TRUE] yes");
+        let received = received.lock().unwrap();
+        assert_eq!(received.path, "gemini-test:generateContent");
+        assert_eq!(received.token.as_deref(), Some("secret"));
+        assert_eq!(
+            received.body,
+            json!({
+                "systemInstruction": {"parts": [{"text": "system"}]},
+                "contents": [{"role": "user", "parts": [{"text": "statement"}]}],
+                "generationConfig": {"responseMimeType": "application/json"},
+            })
+        );
+        assert_eq!(gemini.rate_limit(), 60);
+    }
+
+    #[tokio::test]
+    async fn reports_blocked_prompt() {
+        let (endpoint, _) = mock_server(StatusCode::OK, json!({"promptFeedback": {"blockReason": "SAFETY"}})).await;
+        let gemini = Gemini::new(&config(endpoint));
+
+        let error = gemini.request("system", "statement").await.err().unwrap();
+
+        assert!(matches!(error, GeminiError::Blocked(reason) if reason == "SAFETY"));
+    }
+
+    #[tokio::test]
+    async fn reports_missing_candidates() {
+        let (endpoint, _) = mock_server(StatusCode::OK, json!({"candidates": []})).await;
+        let gemini = Gemini::new(&config(endpoint));
+
+        let error = gemini.request("system", "statement").await.err().unwrap();
+
+        assert!(matches!(error, GeminiError::NoCandidates));
+    }
+
+    #[tokio::test]
+    async fn reports_http_errors() {
+        let (endpoint, _) = mock_server(StatusCode::FORBIDDEN, json!({"error": {"code": 403}})).await;
+        let gemini = Gemini::new(&config(endpoint));
+
+        let error = gemini.request("system", "statement").await.err().unwrap();
+
+        assert!(matches!(error, GeminiError::ReqwestError(e) if e.status() == Some(reqwest::StatusCode::FORBIDDEN)));
+    }
+}
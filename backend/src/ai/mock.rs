@@ -0,0 +1,293 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{normalize, AiApi, Verdict};
+
+/// Offline AI that decides verdicts from a rule file instead of asking a model.
+///
+/// ```toml
+/// default = "reject"
+/// delay = 2.5
+///
+/// [[statement]]
+/// statement = "Socrates is a man."
+/// accept = true
+///
+/// [[implication]]
+/// premises = ["Socrates is a man.", "Every man is mortal."]
+/// conclusion = "Socrates is mortal."
+/// accept = true
+/// explanation = "Classic syllogism."
+///
+/// [[attack]]
+/// attacker = "Socrates is a god."
+/// target = "Socrates is mortal."
+/// accept = true
+/// ```
+///
+/// Statements are compared ignoring case and whitespace. An implication rule applies if all its
+/// premises are among the given ones. An attack rule with a `premise` applies to attacks on the inference of
+/// `target` from that premise, without one to attacks on `target` itself. The first matching rule wins,
+/// `default` decides everything else. Every answer takes `delay` seconds (default 0), like a slow model.
+#[derive(Deserialize, Debug, Default)]
+pub struct Mock {
+    #[serde(default)]
+    default: Policy,
+    #[serde(default)]
+    delay: f64,
+    #[serde(default, rename = "statement")]
+    statements: Vec<StatementRule>,
+    #[serde(default, rename = "implication")]
+    implications: Vec<ImplicationRule>,
+    #[serde(default, rename = "attack")]
+    attacks: Vec<AttackRule>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    Accept,
+    #[default]
+    Reject,
+}
+
+#[derive(Deserialize, Debug)]
+struct StatementRule {
+    statement: String,
+    accept: bool,
+    explanation: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImplicationRule {
+    premises: Vec<String>,
+    conclusion: String,
+    accept: bool,
+    explanation: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AttackRule {
+    attacker: String,
+    target: String,
+    premise: Option<String>,
+    accept: bool,
+    explanation: Option<String>,
+}
+
+pub enum MockError {
+    /// the mock only answers `check_statement`, `check_implication` and `check_attack`.
+    FreeformRequest,
+}
+
+impl Display for MockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockError::FreeformRequest => write!(f, "The mock AI can only check statements, implications and attacks."),
+        }
+    }
+}
+
+/// answer in the format of a real model.
+fn answer(accept: bool, explanation: Option<&str>, reason: &str) -> String {
+    let verdict = Verdict {
+        confidence: Some(1.0),
+        ..Verdict::new(accept, explanation.unwrap_or(reason))
+    };
+    serde_json::to_string(&verdict).unwrap()
+}
+
+impl Mock {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let rules = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        toml::from_str(&rules).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    async fn think(&self) {
+        if self.delay > 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(self.delay)).await;
+        }
+    }
+
+    fn default_answer(&self) -> String {
+        answer(
+            self.default == Policy::Accept,
+            None,
+            "No mock rule matched, applied the default policy.",
+        )
+    }
+}
+
+impl AiApi for Mock {
+    type AiError = MockError;
+
+    fn rate_limit(&self) -> u32 { 0 }
+    fn model(&self) -> String { "mock".into() }
+    /// the rules may change with every start of the server.
+    fn cacheable(&self) -> bool { false }
+
+    async fn request(&self, _system_message: &str, _input: &str) -> Result<String, Self::AiError> {
+        Err(MockError::FreeformRequest)
+    }
+
+    async fn check_statement(&self, statement: &str) -> Result<String, Self::AiError> {
+        self.think().await;
+        let statement = normalize(statement);
+        Ok(self
+            .statements
+            .iter()
+            .find(|rule| normalize(&rule.statement) == statement)
+            .map(|rule| answer(rule.accept, rule.explanation.as_deref(), "Decided by mock rule."))
+            .unwrap_or_else(|| self.default_answer()))
+    }
+
+    async fn check_implication(&self, premises: &[&str], conclusion: &str) -> Result<String, Self::AiError> {
+        self.think().await;
+        let conclusion = normalize(conclusion);
+        let premises: HashSet<String> = premises.iter().map(|premise| normalize(premise)).collect();
+        Ok(self
+            .implications
+            .iter()
+            .find(|rule| {
+                normalize(&rule.conclusion) == conclusion
+                    && rule
+                        .premises
+                        .iter()
+                        .all(|premise| premises.contains(&normalize(premise)))
+            })
+            .map(|rule| answer(rule.accept, rule.explanation.as_deref(), "Decided by mock rule."))
+            .unwrap_or_else(|| self.default_answer()))
+    }
+
+    async fn check_attack(&self, attacker: &str, target: &str, premise: Option<&str>) -> Result<String, Self::AiError> {
+        self.think().await;
+        let (attacker, target, premise) = (normalize(attacker), normalize(target), premise.map(normalize));
+        Ok(self
+            .attacks
+            .iter()
+            .find(|rule| {
+                normalize(&rule.attacker) == attacker
+                    && normalize(&rule.target) == target
+                    && rule.premise.as_deref().map(normalize) == premise
+            })
+            .map(|rule| answer(rule.accept, rule.explanation.as_deref(), "Decided by mock rule."))
+            .unwrap_or_else(|| self.default_answer()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+        default = "reject"
+
+        [[statement]]
+        statement = "Socrates is a man."
+        accept = true
+
+        [[statement]]
+        statement = "The earth is flat."
+        accept = false
+        explanation = "It is round."
+
+        [[implication]]
+        premises = ["Socrates is a man.", "Every man is mortal."]
+        conclusion = "Socrates is mortal."
+        accept = true
+        explanation = "Classic syllogism."
+
+        [[attack]]
+        attacker = "Socrates is a god."
+        target = "Socrates is mortal."
+        accept = true
+
+        [[attack]]
+        attacker = "Socrates is a woman."
+        target = "Socrates is mortal."
+        premise = "Every man is mortal."
+        accept = true
+    "#;
+
+    fn mock() -> Mock { toml::from_str(RULES).unwrap() }
+
+    fn verdict(answer: Result<String, MockError>) -> Verdict { Verdict::parse(&answer.ok().unwrap()).unwrap() }
+
+    #[tokio::test]
+    async fn statement_rules() {
+        let mock = mock();
+        let accepted = verdict(mock.check_statement("  socrates is a MAN. ").await);
+        assert!(accepted.verdict);
+        assert_eq!(accepted.explanation, "Decided by mock rule.");
+        assert_eq!(
+            verdict(mock.check_statement("The earth is flat.").await),
+            Verdict {
+                confidence: Some(1.0),
+                ..Verdict::new(false, "It is round.")
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn implication_needs_all_rule_premises() {
+        let mock = mock();
+        let accepted = mock
+            .check_implication(
+                &["Every man is mortal.", "The sky is blue.", "Socrates is a man."],
+                "Socrates is mortal.",
+            )
+            .await;
+        assert_eq!(verdict(accepted).explanation, "Classic syllogism.");
+
+        let missing_premise = mock
+            .check_implication(&["Socrates is a man."], "Socrates is mortal.")
+            .await;
+        assert!(!verdict(missing_premise).verdict);
+    }
+
+    #[tokio::test]
+    async fn attack_rules_distinguish_inferences() {
+        let mock = mock();
+        assert!(
+            verdict(
+                mock.check_attack("Socrates is a god.", "Socrates is mortal.", None)
+                    .await
+            )
+            .verdict
+        );
+        let on_inference = mock
+            .check_attack(
+                "Socrates is a woman.",
+                "Socrates is mortal.",
+                Some("every man is mortal."),
+            )
+            .await;
+        assert!(verdict(on_inference).verdict);
+        let on_statement = mock
+            .check_attack("Socrates is a woman.", "Socrates is mortal.", None)
+            .await;
+        assert!(!verdict(on_statement).verdict);
+    }
+
+    #[tokio::test]
+    async fn default_policy() {
+        let mut mock = mock();
+        assert!(!verdict(mock.check_statement("unknown").await).verdict);
+        mock.default = Policy::Accept;
+        assert!(verdict(mock.check_statement("unknown").await).verdict);
+        assert!(verdict(mock.check_implication(&["a"], "b").await).verdict);
+    }
+
+    #[tokio::test]
+    async fn rejects_freeform_requests() {
+        assert!(matches!(
+            mock().request("system", "input").await,
+            Err(MockError::FreeformRequest)
+        ));
+    }
+}
@@ -1,7 +1,299 @@
+use std::fmt::Display;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use self::gemini::{Gemini, GeminiError};
+use self::mock::{Mock, MockError};
+use self::openai::{OpenAi, OpenAiError};
+use self::rate_limit::TokenBucket;
+pub use self::verdict::Verdict;
+use crate::Config;
+
+pub mod cache;
+pub mod gemini;
+pub mod mock;
 pub mod openai;
+pub mod rate_limit;
+pub mod verdict;
+
+const SYSTEM_MESSAGE_DIRECT: &str = "The User will give you a statement. Decide whether it is objectively correct.\n
+Answer with a JSON object of the form {\"verdict\": true, \"explanation\": \"...\", \"confidence\": 0.9}:\n
+- verdict: true if the statement is correct, false if not.\n
+- explanation: very brief but exact, in one sentence.\n
+- confidence: how sure you are, between 0 and 1.";
+const SYSTEM_MESSAGE_IMPLICATION: &str = "The User will give you a list of assumptions and a statement. Decide whether you consider the statement to be a logical consequence of the assumptions.\n
+Answer with a JSON object of the form {\"verdict\": true, \"explanation\": \"...\", \"missing_premises\": [], \"confidence\": 0.9}:\n
+- verdict: true if the statement is a consequence, false if not.\n
+- explanation: very brief but exact, in one sentence.\n
+- missing_premises: assumptions that would be needed additionally, empty if the verdict is true.\n
+- confidence: how sure you are, between 0 and 1.";
+const SYSTEM_MESSAGE_ATTACK: &str = "The User will give you a statement, or a conclusion drawn from a premise, and a counter-argument against it. Decide whether the counter-argument, if it is true, refutes the statement or the conclusion.\n
+Answer with a JSON object of the form {\"verdict\": true, \"explanation\": \"...\", \"confidence\": 0.9}:\n
+- verdict: true if the counter-argument refutes it, false if not.\n
+- explanation: very brief but exact, in one sentence.\n
+- confidence: how sure you are, between 0 and 1.";
+const IMPLICATION_PRE: &str = "Assume, the following assumptions would all be true:\n";
+const IMPLICATION_MID: &str = "\nNow, under this assumption, evaluate if the following statement is a consequence:\n";
+
+/// canonical form of a statement for comparisons: lowercase, with single spaces between words.
+pub fn normalize(statement: &str) -> String {
+    statement
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
 
 pub trait AiApi {
-    type AiError;
+    type AiError: Display;
     fn rate_limit(&self) -> u32;
-    async fn request(&mut self, input: String) -> Result<String, Self::AiError>;
+    /// identifies the model that answers, verdicts are cached per model.
+    fn model(&self) -> String;
+    /// whether verdicts of this API may be cached. Answers that depend on local configuration may not.
+    fn cacheable(&self) -> bool { true }
+    async fn request(&self, system_message: &str, input: &str) -> Result<String, Self::AiError>;
+
+    /// ask whether `statement` is true by itself. The answer should parse as `Verdict`.
+    async fn check_statement(&self, statement: &str) -> Result<String, Self::AiError> {
+        self.request(SYSTEM_MESSAGE_DIRECT, statement).await
+    }
+
+    /// ask whether `conclusion` follows from `premises`. The answer should parse as `Verdict`.
+    async fn check_implication(&self, premises: &[&str], conclusion: &str) -> Result<String, Self::AiError> {
+        let user_message = format!(
+            "{}{}{}{}",
+            IMPLICATION_PRE,
+            premises.join("\n"),
+            IMPLICATION_MID,
+            conclusion
+        );
+        self.request(SYSTEM_MESSAGE_IMPLICATION, &user_message).await
+    }
+
+    /// ask whether `attacker` refutes `target`, or refutes concluding `target` from `premise` if given.
+    /// The answer should parse as `Verdict`.
+    async fn check_attack(&self, attacker: &str, target: &str, premise: Option<&str>) -> Result<String, Self::AiError> {
+        let user_message = match premise {
+            Some(premise) => format!(
+                "Premise:\n{}\nConclusion:\n{}\nCounter-argument against drawing this conclusion:\n{}",
+                premise, target, attacker
+            ),
+            None => format!("Statement:\n{}\nCounter-argument:\n{}", target, attacker),
+        };
+        self.request(SYSTEM_MESSAGE_ATTACK, &user_message).await
+    }
+}
+
+/// the AI providers shared by all games. Every verdict is a consensus of `samples` answers of each member.
+pub struct SharedAi<A: AiApi> {
+    pub members: Vec<Member<A>>,
+    pub samples: u32,
+    pub quorum: usize,
+}
+
+/// one provider of `SharedAi`, with the rate limit of its endpoint.
+pub struct Member<A: AiApi> {
+    pub api: A,
+    pub limit: Mutex<TokenBucket>,
+}
+
+impl<A: AiApi> SharedAi<A> {
+    /// `quorum` defaults to more than half of all answers, and is at least one and at most all answers.
+    pub fn new(apis: Vec<A>, samples: u32, quorum: Option<u32>) -> Self {
+        let members: Vec<_> = apis
+            .into_iter()
+            .map(|api| Member {
+                limit: Mutex::new(TokenBucket::per_minute(api.rate_limit())),
+                api,
+            })
+            .collect();
+        assert!(!members.is_empty(), "at least one AI provider is needed");
+        let samples = samples.max(1);
+        let answers = members.len() * samples as usize;
+        let quorum = quorum.map_or(answers / 2 + 1, |quorum| quorum as usize);
+        let clamped = quorum.clamp(1, answers);
+        if clamped != quorum {
+            tracing::warn!(
+                "a quorum of {} is impossible with {} answer(s), using {}",
+                quorum,
+                answers,
+                clamped
+            );
+        }
+        Self {
+            members,
+            samples,
+            quorum: clamped,
+        }
+    }
+
+    /// whether the verdicts of all members may be cached.
+    pub fn cacheable(&self) -> bool { self.members.iter().all(|member| member.api.cacheable()) }
+
+    /// identifies the models and quorum answering, verdicts are cached per model.
+    pub fn model(&self) -> String {
+        let models: Vec<_> = self.members.iter().map(|member| member.api.model()).collect();
+        if models.len() == 1 && self.samples == 1 {
+            return models.join("");
+        }
+        format!("{} x{} quorum {}", models.join(", "), self.samples, self.quorum)
+    }
+}
+
+/// which AI backend answers the questions of all games, see `Config::ai_provider`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Openai,
+    GoogleGemini,
+    /// any server speaking the OpenAI chat completions format, f.ex. Ollama or llama.cpp.
+    Local,
+    /// answers from the rule file `Config::mock_rules`, without network access.
+    Mock,
+}
+
+/// the AI backend selected in the config.
+pub enum Provider {
+    OpenAi(OpenAi),
+    Gemini(Gemini),
+    Mock(Mock),
+    Disabled,
+}
+
+pub enum ProviderError {
+    Disabled,
+    OpenAi(OpenAiError),
+    Gemini(GeminiError),
+    Mock(MockError),
+}
+
+impl Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderError::Disabled => write!(f, "The AI is disabled on this server."),
+            ProviderError::OpenAi(e) => e.fmt(f),
+            ProviderError::Gemini(e) => e.fmt(f),
+            ProviderError::Mock(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Provider {
+    pub fn from_config(config: &Config, kind: ProviderKind) -> Self {
+        let endpoint = match kind {
+            ProviderKind::Openai => &config.openai,
+            ProviderKind::GoogleGemini => &config.google_gemini,
+            ProviderKind::Local => &config.local,
+            ProviderKind::Mock => return Provider::Mock(Mock::load(&config.mock_rules).expect("invalid mock rules")),
+        };
+        if endpoint.disabled {
+            return Provider::Disabled;
+        }
+        match kind {
+            ProviderKind::GoogleGemini => Provider::Gemini(Gemini::new(endpoint)),
+            _ => Provider::OpenAi(OpenAi::new(endpoint)),
+        }
+    }
+}
+
+impl AiApi for Provider {
+    type AiError = ProviderError;
+
+    fn rate_limit(&self) -> u32 {
+        match self {
+            Provider::OpenAi(api) => api.rate_limit(),
+            Provider::Gemini(api) => api.rate_limit(),
+            Provider::Mock(api) => api.rate_limit(),
+            Provider::Disabled => 0,
+        }
+    }
+
+    fn model(&self) -> String {
+        match self {
+            Provider::OpenAi(api) => api.model(),
+            Provider::Gemini(api) => api.model(),
+            Provider::Mock(api) => api.model(),
+            Provider::Disabled => "disabled".into(),
+        }
+    }
+
+    fn cacheable(&self) -> bool {
+        match self {
+            Provider::OpenAi(api) => api.cacheable(),
+            Provider::Gemini(api) => api.cacheable(),
+            Provider::Mock(api) => api.cacheable(),
+            Provider::Disabled => false,
+        }
+    }
+
+    async fn request(&self, system_message: &str, input: &str) -> Result<String, Self::AiError> {
+        match self {
+            Provider::OpenAi(api) => api.request(system_message, input).await.map_err(ProviderError::OpenAi),
+            Provider::Gemini(api) => api.request(system_message, input).await.map_err(ProviderError::Gemini),
+            Provider::Mock(api) => api.request(system_message, input).await.map_err(ProviderError::Mock),
+            Provider::Disabled => Err(ProviderError::Disabled),
+        }
+    }
+
+    async fn check_statement(&self, statement: &str) -> Result<String, Self::AiError> {
+        match self {
+            Provider::OpenAi(api) => api.check_statement(statement).await.map_err(ProviderError::OpenAi),
+            Provider::Gemini(api) => api.check_statement(statement).await.map_err(ProviderError::Gemini),
+            Provider::Mock(api) => api.check_statement(statement).await.map_err(ProviderError::Mock),
+            Provider::Disabled => Err(ProviderError::Disabled),
+        }
+    }
+
+    async fn check_implication(&self, premises: &[&str], conclusion: &str) -> Result<String, Self::AiError> {
+        match self {
+            Provider::OpenAi(api) => api
+                .check_implication(premises, conclusion)
+                .await
+                .map_err(ProviderError::OpenAi),
+            Provider::Gemini(api) => api
+                .check_implication(premises, conclusion)
+                .await
+                .map_err(ProviderError::Gemini),
+            Provider::Mock(api) => api
+                .check_implication(premises, conclusion)
+                .await
+                .map_err(ProviderError::Mock),
+            Provider::Disabled => Err(ProviderError::Disabled),
+        }
+    }
+
+    async fn check_attack(&self, attacker: &str, target: &str, premise: Option<&str>) -> Result<String, Self::AiError> {
+        match self {
+            Provider::OpenAi(api) => api
+                .check_attack(attacker, target, premise)
+                .await
+                .map_err(ProviderError::OpenAi),
+            Provider::Gemini(api) => api
+                .check_attack(attacker, target, premise)
+                .await
+                .map_err(ProviderError::Gemini),
+            Provider::Mock(api) => api
+                .check_attack(attacker, target, premise)
+                .await
+                .map_err(ProviderError::Mock),
+            Provider::Disabled => Err(ProviderError::Disabled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quorum(quorum: Option<u32>) -> usize { SharedAi::new(vec![Mock::default(), Mock::default()], 2, quorum).quorum }
+
+    #[test]
+    fn quorum_is_reachable() {
+        assert_eq!(quorum(None), 3);
+        assert_eq!(quorum(Some(2)), 2);
+        assert_eq!(quorum(Some(0)), 1, "no verdict without any accepting answer");
+        assert_eq!(quorum(Some(5)), 4, "more than all answers can never accept");
+    }
 }
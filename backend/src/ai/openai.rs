@@ -1,46 +1,71 @@
+use std::fmt::Display;
+
 use reqwest::{self};
 use serde::{Deserialize, Serialize};
 
 use super::AiApi;
+use crate::EndpointConfig;
+
+const DEFAULT_MODEL: &str = "gpt-3.5-turbo";
 
-struct OpenAi {
+/// client for the OpenAI chat completions api, or any endpoint compatible with it.
+pub struct OpenAi {
     model: String,
     token: String,
     endpoint: String,
+    rate_limit: u32,
+}
+
+impl OpenAi {
+    pub fn new(config: &EndpointConfig) -> Self {
+        Self {
+            model: config.model.clone().unwrap_or(DEFAULT_MODEL.into()),
+            token: config.token.clone(),
+            endpoint: config.endpoint.clone(),
+            rate_limit: config.rate_limit.unwrap_or(0),
+        }
+    }
 }
 
 pub enum OpenAiError {
     NoChoices,
-    WrongFormat,
     RewestError(reqwest::Error),
 }
 
-impl From<reqwest::Error> for OpenAiError {
-    fn from(error: reqwest::Error) -> Self {
-        Self::RewestError(error)
+impl Display for OpenAiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpenAiError::NoChoices => write!(f, "The AI did not answer."),
+            OpenAiError::RewestError(e) => write!(f, "Could not reach the AI: {}", e),
+        }
     }
 }
 
-const OPENAI_SYSTEM_MESSAGE: &'static str = include_str!("system_message.txt");
+impl From<reqwest::Error> for OpenAiError {
+    fn from(error: reqwest::Error) -> Self { Self::RewestError(error) }
+}
 
 impl AiApi for OpenAi {
     type AiError = OpenAiError;
 
-    fn rate_limit(&self) -> u32 {
-        todo!()
-    }
+    fn rate_limit(&self) -> u32 { self.rate_limit }
+    fn model(&self) -> String { self.model.clone() }
 
-    async fn request(&mut self, input: String) -> Result<String, Self::AiError> {
+    async fn request(&self, system_message: &str, input: &str) -> Result<String, Self::AiError> {
         let request = OpenAiRequest::new(&self.model)
-            .append_message(Role::System, OPENAI_SYSTEM_MESSAGE)
+            .response_format(Some(ResponseFormat::JsonObject))
+            .append_message(Role::System, system_message)
             .append_message(Role::User, input);
 
-        let response = reqwest::Client::new()
-            .post(&self.endpoint)
-            .bearer_auth(&self.token)
-            .json(&request)
+        let mut request = reqwest::Client::new().post(&self.endpoint).json(&request);
+        // local endpoints usually do not need a token
+        if !self.token.is_empty() {
+            request = request.bearer_auth(&self.token);
+        }
+        let response = request
             .send()
             .await?
+            .error_for_status()?
             .json::<OpenAiResponse>()
             .await?;
 
@@ -48,13 +73,7 @@ impl AiApi for OpenAi {
             return Err(OpenAiError::NoChoices);
         }
 
-        let content = response.choices.get(0).unwrap().message.content.to_owned();
-
-        if !content.starts_with("[TRUE]") || !content.starts_with("[FALSE]") {
-            return Err(OpenAiError::WrongFormat);
-        }
-
-        Ok(content)
+        Ok(response.choices.first().unwrap().message.content.to_owned())
     }
 }
 
@@ -85,6 +104,7 @@ impl OpenAiRequest {
 #[serde(rename_all = "snake_case")]
 struct OpenAiRequest {
     model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<ResponseFormat>,
     messages: Vec<Message>,
 }
@@ -101,6 +121,7 @@ enum ResponseFormat {
 enum Role {
     System,
     User,
+    Assistant,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -114,15 +135,6 @@ struct OpenAiResponse {
     choices: Vec<Choice>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "snake_case")]
-enum FinishReason {
-    Stop,
-    Length,
-    ContentFilter,
-    ToolCalls,
-}
-
 #[derive(Deserialize, Debug)]
 struct Choice {
     message: Message,
@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+/// Token bucket allowing `per_minute` requests per minute, with bursts of up to `per_minute` requests.
+/// A limit of 0 means unlimited.
+pub struct TokenBucket {
+    per_minute: u32,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn per_minute(per_minute: u32) -> Self { Self::per_minute_at(per_minute, Instant::now()) }
+
+    fn per_minute_at(per_minute: u32, now: Instant) -> Self {
+        Self {
+            per_minute,
+            tokens: per_minute as f64,
+            updated: now,
+        }
+    }
+
+    /// time until the next request is allowed, zero if it is allowed right now.
+    pub fn wait_time(&mut self) -> Duration { self.wait_time_for(1) }
+
+    /// time until `requests` requests at once are allowed. More than `per_minute` are allowed with a full bucket.
+    pub fn wait_time_for(&mut self, requests: u32) -> Duration { self.wait_time_at(requests, Instant::now()) }
+
+    /// use up one request. Check `wait_time` first.
+    pub fn take(&mut self) { self.take_for(1) }
+
+    /// use up `requests` requests. Check `wait_time_for` first.
+    pub fn take_for(&mut self, requests: u32) { self.take_at(requests, Instant::now()) }
+
+    fn wait_time_at(&mut self, requests: u32, now: Instant) -> Duration {
+        if self.per_minute == 0 {
+            return Duration::ZERO;
+        }
+        self.refill(now);
+        let needed = requests.min(self.per_minute) as f64;
+        if self.tokens >= needed {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((needed - self.tokens) * 60.0 / self.per_minute as f64)
+        }
+    }
+
+    fn take_at(&mut self, requests: u32, now: Instant) {
+        if self.per_minute == 0 {
+            return;
+        }
+        self.refill(now);
+        self.tokens -= requests.min(self.per_minute) as f64;
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_minute as f64 / 60.0).min(self.per_minute as f64);
+        self.updated = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_bursts_then_waits() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute_at(2, start);
+        for _ in 0..2 {
+            assert_eq!(bucket.wait_time_at(1, start), Duration::ZERO);
+            bucket.take_at(1, start);
+        }
+        assert_eq!(bucket.wait_time_at(1, start), Duration::from_secs(30));
+        assert_eq!(
+            bucket.wait_time_at(1, start + Duration::from_secs(20)),
+            Duration::from_secs(10)
+        );
+        assert_eq!(bucket.wait_time_at(1, start + Duration::from_secs(30)), Duration::ZERO);
+    }
+
+    #[test]
+    fn refills_up_to_capacity() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute_at(1, start);
+        bucket.take_at(1, start);
+        let later = start + Duration::from_secs(600);
+        bucket.take_at(1, later);
+        assert_eq!(bucket.wait_time_at(1, later), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn waits_for_several_requests() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute_at(6, start);
+        bucket.take_at(4, start);
+        assert_eq!(bucket.wait_time_at(2, start), Duration::ZERO);
+        assert_eq!(bucket.wait_time_at(3, start), Duration::from_secs(10));
+        assert_eq!(bucket.wait_time_at(10, start), Duration::from_secs(40));
+    }
+
+    #[test]
+    fn zero_is_unlimited() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute_at(0, start);
+        for _ in 0..100 {
+            bucket.take_at(1, start);
+        }
+        assert_eq!(bucket.wait_time_at(1, start), Duration::ZERO);
+    }
+}
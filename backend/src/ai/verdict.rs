@@ -0,0 +1,175 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// The answer of an AI to one question, as requested in the system messages.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Verdict {
+    /// whether the statement is true, or follows from the premises.
+    pub verdict: bool,
+    pub explanation: String,
+    /// premises the AI thinks are needed additionally. Only used for implications.
+    #[serde(default)]
+    pub missing_premises: Vec<String>,
+    /// between 0 and 1, if the AI gave one.
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    /// answered from the verdict cache instead of asking the AI again.
+    #[serde(skip)]
+    pub cached: bool,
+}
+
+impl Verdict {
+    pub fn new(verdict: bool, explanation: impl Into<String>) -> Self {
+        Self {
+            verdict,
+            explanation: explanation.into(),
+            missing_premises: Vec::new(),
+            confidence: None,
+            cached: false,
+        }
+    }
+
+    /// parse an AI answer. Accepts the requested JSON object (also wrapped in a markdown code block) and
+    /// falls back to answers starting with '[TRUE]' or '[FALSE]'. Returns `None` for anything else.
+    pub fn parse(answer: &str) -> Option<Self> {
+        let answer = answer.trim();
+        let json = answer
+            .strip_prefix("```json")
+            .or_else(|| answer.strip_prefix("```"))
+            .and_then(|answer| answer.strip_suffix("```"))
+            .unwrap_or(answer);
+        if let Ok(verdict) = serde_json::from_str::<Verdict>(json) {
+            return Some(verdict);
+        }
+        if let Some(explanation) = answer.strip_prefix("[TRUE]") {
+            Some(Self::new(true, explanation.trim()))
+        } else {
+            answer
+                .strip_prefix("[FALSE]")
+                .map(|explanation| Self::new(false, explanation.trim()))
+        }
+    }
+}
+
+/// combine the answers of several models, or several samples of one model, into one verdict.
+/// Accepts if at least `quorum` answers accept, the explanation lists every answer so players can see dissent.
+/// A single answer is returned unchanged, if no answer is usable the first failure is returned.
+pub fn consensus<E: Display>(answers: Vec<(String, Result<Verdict, E>)>, quorum: usize) -> Result<Verdict, E> {
+    if answers.len() == 1 || answers.iter().all(|(_, answer)| answer.is_err()) {
+        return answers.into_iter().next().expect("at least one answer").1;
+    }
+    let accepting = answers
+        .iter()
+        .filter(|(_, answer)| matches!(answer, Ok(verdict) if verdict.verdict))
+        .count();
+    let mut explanation = format!("{} of {} answers accept, {} needed.", accepting, answers.len(), quorum);
+    let mut missing_premises: Vec<String> = Vec::new();
+    for (model, answer) in answers {
+        match answer {
+            Ok(verdict) => {
+                let decision = if verdict.verdict { "accepts" } else { "rejects" };
+                explanation.push_str(&format!("\n{} {}: {}", model, decision, verdict.explanation));
+                for premise in verdict.missing_premises {
+                    if !missing_premises.contains(&premise) {
+                        missing_premises.push(premise);
+                    }
+                }
+            }
+            Err(failure) => explanation.push_str(&format!("\n{} failed: {}", model, failure)),
+        }
+    }
+    Ok(Verdict {
+        missing_premises,
+        ..Verdict::new(accepting >= quorum, explanation)
+    })
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.explanation)?;
+        if !self.missing_premises.is_empty() {
+            write!(f, " Missing premises: {}", self.missing_premises.join("; "))?;
+        }
+        if let Some(confidence) = self.confidence {
+            write!(f, " (confidence: {:.0}%)", confidence * 100.0)?;
+        }
+        if self.cached {
+            write!(f, " (cached)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json() {
+        let verdict = Verdict::parse(
+            r#"{"verdict": false, "explanation": "Not all men.", "missing_premises": ["Every man is mortal."], "confidence": 0.9}"#,
+        )
+        .unwrap();
+        assert!(!verdict.verdict);
+        assert_eq!(verdict.missing_premises, vec!["Every man is mortal."]);
+        assert_eq!(
+            verdict.to_string(),
+            "Not all men. Missing premises: Every man is mortal. (confidence: 90%)"
+        );
+    }
+
+    #[test]
+    fn parses_json_code_block() {
+        let verdict = Verdict::parse("```json\n{\"verdict\": true, \"explanation\": \"Yes.\"}\n```").unwrap();
+        assert_eq!(verdict, Verdict::new(true, "Yes."));
+    }
+
+    #[test]
+    fn falls_back_to_prefix() {
+        assert_eq!(Verdict::parse("[TRUE] Yes."), Some(Verdict::new(true, "Yes.")));
+        assert_eq!(Verdict::parse(" [FALSE] No."), Some(Verdict::new(false, "No.")));
+    }
+
+    #[test]
+    fn consensus_needs_quorum() {
+        let answers = || {
+            vec![
+                ("a".to_string(), Ok(Verdict::new(true, "Yes."))),
+                ("b".to_string(), Err("timeout")),
+                (
+                    "c".to_string(),
+                    Ok(Verdict {
+                        missing_premises: vec!["Every man is mortal.".into()],
+                        ..Verdict::new(false, "No.")
+                    }),
+                ),
+            ]
+        };
+        let verdict = consensus(answers(), 2).unwrap();
+        assert!(!verdict.verdict);
+        assert_eq!(
+            verdict.explanation,
+            "1 of 3 answers accept, 2 needed.\na accepts: Yes.\nb failed: timeout\nc rejects: No."
+        );
+        assert_eq!(verdict.missing_premises, vec!["Every man is mortal."]);
+        assert!(consensus(answers(), 1).unwrap().verdict);
+    }
+
+    #[test]
+    fn consensus_of_one_is_unchanged() {
+        let verdict = Verdict::new(true, "Yes.");
+        assert_eq!(
+            consensus(vec![("a".to_string(), Ok::<_, &str>(verdict.clone()))], 1),
+            Ok(verdict)
+        );
+        let failures = vec![("a".to_string(), Err("first")), ("b".to_string(), Err("second"))];
+        assert_eq!(consensus::<&str>(failures, 1), Err("first"));
+    }
+
+    #[test]
+    fn rejects_malformed_answers() {
+        assert_eq!(Verdict::parse("I think so."), None);
+        assert_eq!(Verdict::parse(r#"{"explanation": "no verdict"}"#), None);
+    }
+}
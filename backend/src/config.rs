@@ -1,22 +1,99 @@
 use std::path::PathBuf;
 
-use serde::Deserialize;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::ai::ProviderKind;
 
 #[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
 pub struct Config {
     pub address: String,
     pub serve_dir: PathBuf,
+    pub database: PathBuf,
 
+    /// which of the endpoints below is used to check statements.
+    pub ai_provider: ProviderKind,
     pub openai: EndpointConfig,
     pub google_gemini: EndpointConfig,
+    /// OpenAI compatible endpoint, f.ex. Ollama or llama.cpp.
+    pub local: EndpointConfig,
+    /// rule file for `ai_provider = "mock"`, see `ai::mock::Mock`.
+    pub mock_rules: PathBuf,
+    pub ai_quota: AiQuota,
+    pub consensus: Consensus,
+    pub limits: Limits,
+}
+
+/// ask several providers, or one provider several times, before accepting a verdict.
+/// Every provider in `providers` (default: only `ai_provider`) is asked `samples` times, and the statement
+/// is accepted if at least `quorum` answers accept it (default: more than half of them, at most all of them).
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct Consensus {
+    pub providers: Vec<ProviderKind>,
+    pub samples: u32,
+    pub quorum: Option<u32>,
+}
+
+impl Default for Consensus {
+    fn default() -> Self {
+        Self {
+            providers: Vec::new(),
+            samples: 1,
+            quorum: None,
+        }
+    }
+}
+
+impl Config {
+    /// the providers asked for every verdict.
+    pub fn providers(&self) -> Vec<ProviderKind> {
+        if self.consensus.providers.is_empty() {
+            vec![self.ai_provider]
+        } else {
+            self.consensus.providers.clone()
+        }
+    }
+}
+
+/// how many AI requests per minute a single game or client may make, 0 means unlimited.
+/// The limit of the endpoint itself (`EndpointConfig::rate_limit`) is shared by all games.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct AiQuota {
+    pub game: u32,
+    pub client: u32,
+}
+
+impl Default for AiQuota {
+    fn default() -> Self { Self { game: 4, client: 2 } }
+}
+
+/// what clients may send: websocket messages of at most `message_size` bytes,
+/// statements of at most `statement_length` characters.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct Limits {
+    pub message_size: usize,
+    pub statement_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            message_size: 64 * 1024,
+            statement_length: 2000,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
 pub struct EndpointConfig {
     pub disabled: bool,
     pub token: String,
     pub endpoint: String,
+    pub model: Option<String>,
     pub rate_limit: Option<u32>,
 }
 
@@ -26,6 +103,7 @@ impl Default for EndpointConfig {
             disabled: true,
             token: String::new(),
             endpoint: String::new(),
+            model: None,
             rate_limit: Some(0),
         }
     }
@@ -36,7 +114,15 @@ impl Default for Config {
         Self {
             address: "0.0.0.0:3000".into(),
             serve_dir: "argue-react/dist".into(),
-            ..Default::default()
+            database: "argue.sqlite".into(),
+            ai_provider: ProviderKind::default(),
+            openai: EndpointConfig::default(),
+            google_gemini: EndpointConfig::default(),
+            local: EndpointConfig::default(),
+            mock_rules: "mock_rules.toml".into(),
+            ai_quota: AiQuota::default(),
+            consensus: Consensus::default(),
+            limits: Limits::default(),
         }
     }
 }
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::ai::{Provider, SharedAi};
+use crate::model::{check_length, GameState, ImportError, ImportFormat, LoadError, ProofError, AI};
+use crate::storage::Storage;
+use crate::{AiQuota, Limits};
+
+/// Stable identifier of a game, handed out by `/api/create` and used in `/ws/{game_id}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GameId(Uuid);
+
+impl GameId {
+    pub fn new() -> Self { Self(Uuid::new_v4()) }
+}
+
+impl Display for GameId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { self.0.fmt(f) }
+}
+
+/// all games known to the server. Games that are not in memory are restored from storage on access.
+pub struct Games {
+    games: Mutex<HashMap<GameId, Arc<Mutex<GameState>>>>,
+    storage: Arc<Storage>,
+    ai: Arc<SharedAi<Provider>>,
+    quota: AiQuota,
+    limits: Limits,
+}
+
+impl Games {
+    pub fn new(storage: Storage, ai: SharedAi<Provider>, quota: AiQuota, limits: Limits) -> Self {
+        Self {
+            games: Mutex::default(),
+            storage: Arc::new(storage),
+            ai: Arc::new(ai),
+            quota,
+            limits,
+        }
+    }
+
+    /// allocate a new game with the given root statement.
+    pub async fn create(&self, root_statement: String) -> Result<GameId, ProofError> {
+        check_length(&root_statement, self.limits)?;
+        let id = GameId::new();
+        let game = GameState::new(id, root_statement, self.storage.clone(), self.new_ai(), self.limits);
+        self.games.lock().await.insert(id, game.shared());
+        Ok(id)
+    }
+
+    /// allocate a new game from an argument map of another tool.
+    pub async fn import(&self, format: ImportFormat, text: &str) -> Result<GameId, ImportError> {
+        let id = GameId::new();
+        let game = GameState::import(id, format, text, self.storage.clone(), self.new_ai(), self.limits)?;
+        self.games.lock().await.insert(id, game.shared());
+        Ok(id)
+    }
+
+    /// allocate a new game from a document of `/api/games/{id}/save`.
+    pub async fn load_saved(&self, text: &str) -> Result<GameId, LoadError> {
+        let id = GameId::new();
+        let game = GameState::from_saved(id, text, self.storage.clone(), self.new_ai(), self.limits)?;
+        self.games.lock().await.insert(id, game.shared());
+        Ok(id)
+    }
+
+    pub async fn get(&self, id: GameId) -> Option<Arc<Mutex<GameState>>> {
+        if let Some(game) = self.games.lock().await.get(&id) {
+            return Some(game.clone());
+        }
+        // restored without holding the lock, so reading the storage does not hold up other games.
+        // If the game was restored meanwhile by another request, that copy is kept.
+        let game = GameState::load(id, self.storage.clone(), self.new_ai(), self.limits).await?;
+        let mut games = self.games.lock().await;
+        Some(games.entry(id).or_insert_with(|| game.shared()).clone())
+    }
+
+    pub fn limits(&self) -> Limits { self.limits }
+
+    fn new_ai(&self) -> AI<Provider> { AI::new(self.ai.clone(), self.quota, self.storage.clone()) }
+}
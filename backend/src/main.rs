@@ -1,15 +1,21 @@
-use axum::routing::{get, get_service};
+use std::sync::Arc;
+
+use axum::routing::{get, get_service, post};
 use axum::Router;
 use tower_http::services::ServeDir;
 
 mod ai;
 mod config;
+mod games;
 mod model;
-mod openai;
 mod routes;
 mod socket_handler;
+mod storage;
 
+use ai::{AiApi, Provider, SharedAi};
 pub use config::*;
+use games::Games;
+use storage::Storage;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
@@ -18,18 +24,53 @@ async fn main() -> std::io::Result<()> {
 
     tracing_subscriber::fmt().with_max_level(tracing::Level::TRACE).init();
 
+    if std::env::args().nth(1).as_deref() == Some("--schema") {
+        println!("{}", serde_json::to_string_pretty(&model::schema()).unwrap());
+        return Ok(());
+    }
+
     let config_path = std::env::var("CONFIG_FILE").unwrap_or("argue.toml".into());
     let config: Config = std::fs::read_to_string(config_path)
         .map(|s| toml::from_str(&s).unwrap())
         .unwrap_or_default();
 
-    let listener = tokio::net::TcpListener::bind(config.address).await?;
-    let static_service = ServeDir::new(config.serve_dir);
+    let listener = tokio::net::TcpListener::bind(&config.address).await?;
+    let static_service = ServeDir::new(&config.serve_dir);
+
+    let storage = Storage::open(&config.database).expect("could not open database");
+    let providers = config.providers();
+    let ai = SharedAi::new(
+        providers
+            .iter()
+            .map(|kind| Provider::from_config(&config, *kind))
+            .collect(),
+        config.consensus.samples,
+        config.consensus.quorum,
+    );
+    for (kind, member) in providers.iter().zip(&ai.members) {
+        tracing::info!(
+            "using AI provider {:?} with a rate limit of {} request(s) per minute",
+            kind,
+            member.api.rate_limit()
+        );
+    }
+    tracing::info!(
+        "accepting verdicts with {} of {} answer(s)",
+        ai.quorum,
+        ai.members.len() * ai.samples as usize
+    );
+    let games = Arc::new(Games::new(storage, ai, config.ai_quota, config.limits));
 
     let app = Router::new()
-        .route("/api/create", get(routes::create_game))
-        // .route("/ws", get(socket_handler::ws_route_handler))
-        .nest_service("/", get(get_service(static_service)));
+        .route("/api/create", post(routes::create_game))
+        .route("/api/import", post(routes::import_game))
+        .route("/api/load", post(routes::load_game))
+        .route("/api/games/:game_id/save", get(routes::save_game))
+        .route("/api/games/:game_id/export", get(routes::export_game))
+        .route("/api/protocol", get(routes::protocol_schema))
+        .route("/ws/:game_id", get(socket_handler::ws_route_handler))
+        .with_state(games)
+        .fallback_service(get_service(static_service));
 
     axum::serve(listener, app).await
 }
@@ -1,254 +1,1303 @@
-use std::env;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::future::Future;
 use std::result::Result;
-use std::time::Instant;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 
+use crate::ai::cache::CacheKey;
+use crate::ai::rate_limit::TokenBucket;
+use crate::ai::verdict::consensus;
+use crate::ai::{AiApi, Provider, SharedAi, Verdict};
+use crate::games::GameId;
 use crate::model::ClientMessage::*;
+use crate::storage::{Storage, StoredComment};
+use crate::{AiQuota, Limits};
 
-use axum::extract::ws::{Message, WebSocket};
-use futures_util::stream::SplitSink;
-use futures_util::SinkExt;
+use axum::extract::ws::{CloseFrame, Message};
+use futures_util::future::join_all;
 use generational_arena::Index;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
-use self::proof::{ProofError, ProofState, TreeState};
+pub use self::export::ExportFormat;
+pub use self::interchange::{ImportError, ImportFormat};
+pub use self::proof::ProofError;
+use self::proof::{AttackTarget, ProofState, TreeState};
+use self::protocol::IndexSchema;
+pub use self::protocol::{schema, ProtocolError, PROTOCOL_VERSION};
+use self::question::Question;
+pub use self::save::LoadError;
+use self::save::SavedGame;
+use self::verification::{Verification, VerificationKind};
+mod export;
+mod history;
+mod interchange;
 mod proof;
+mod protocol;
+mod question;
+mod save;
+mod verification;
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct StatementDTO {
+    #[schemars(with = "IndexSchema")]
     id: Index,
     statement: String,
     state: ProofState,
+    /// under an attack the AI accepted, which is not defeated itself. Defeated statements do not count as proven.
+    defeated: bool,
+    /// every premise group is rejected or contains a refuted, defeated or unsupported premise.
+    unsupported: bool,
+    /// the AI is checking the statement, one of its premise groups or one of its attacks.
+    verifying: bool,
+    #[schemars(with = "Vec<IndexSchema>")]
     parents: Vec<Index>,
+    /// premises of all groups.
+    #[schemars(with = "Vec<IndexSchema>")]
     children: Vec<Index>,
+    groups: Vec<GroupDTO>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
+pub struct GroupDTO {
+    #[schemars(with = "Vec<IndexSchema>")]
+    premises: Vec<Index>,
+    accepted: bool,
+    rejected: bool,
+    /// a premise was edited since the AI judged the group, the verdict does not count until it is checked again.
+    stale: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
 pub struct TreeStateDTO {
     statements: Vec<StatementDTO>,
+    attacks: Vec<proof::Attack>,
+    #[schemars(with = "IndexSchema")]
     root: Index,
+    revision: u64,
 }
 
-#[derive(Serialize)]
+/// what changed from revision `base` to `revision`. Clients that are not at `base` ask for the changes they missed.
+#[derive(Serialize, JsonSchema)]
+pub struct TreeDiffDTO {
+    base: u64,
+    revision: u64,
+    /// statements that were added or changed, including derived fields like `defeated`.
+    statements: Vec<StatementDTO>,
+    #[schemars(with = "Vec<IndexSchema>")]
+    removed: Vec<Index>,
+    /// all attacks, if any of them changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attacks: Option<Vec<proof::Attack>>,
+}
+
+#[derive(Serialize, JsonSchema)]
 pub enum ServerMessage {
-    NewNodeId(Index),
+    /// sent first on every connection.
+    Welcome {
+        protocol: u32,
+    },
+    NewNodeId(#[schemars(with = "IndexSchema")] Index),
     GameState(TreeStateDTO),
-    Comment { id: Index, comment: String, success: bool },
+    GameDiff(TreeDiffDTO),
+    Comment {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+        comment: String,
+        success: bool,
+    },
     Win,
-    AICooldown { seconds: u64 },
+    AICooldown {
+        seconds: u64,
+    },
+    /// all verdicts of the AI on a statement, oldest first.
+    NodeHistory {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+        verifications: Vec<Verification>,
+    },
     Error(ProofError),
+    ProtocolError(ProtocolError),
+    /// the request was carried out and has no other result.
+    Ack,
+}
+
+/// chosen by the client to match replies to its requests.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug, JsonSchema)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(u64),
+    Text(String),
 }
-#[derive(Deserialize, Serialize)]
+
+/// a message sent only to the client that made the request `request_id`.
+#[derive(Serialize, JsonSchema)]
+struct ServerReply<'a> {
+    request_id: &'a RequestId,
+    message: &'a ServerMessage,
+}
+
+/// a client message, either bare or in an envelope with a request id.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ClientRequest {
+    Tagged {
+        request_id: RequestId,
+        message: ClientMessage,
+    },
+    Bare(ClientMessage),
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
 pub enum ClientMessage {
+    /// the protocol version the client speaks, should be sent first. Clients of another version are disconnected.
+    Hello {
+        protocol: u32,
+    },
     GetGameState,
-    Add { statement: String },
-    Delete { id: Index },
-    Edit { id: Index, statement: String },
-    Link { premise: Index, conclusion: Index },
-    Unlink { premise: Index, conclusion: Index },
-    ProveDirect { id: Index },
-    ProveImplication { id: Index },
+    /// the changes after revision `since`, or the whole tree if they are not remembered anymore.
+    GetChanges {
+        since: u64,
+    },
+    GetNodeHistory {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+    },
+    Add {
+        statement: String,
+    },
+    Delete {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+    },
+    Edit {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+        statement: String,
+    },
+    /// adds `premise` to a premise group of `conclusion`, the next free group index starts a new group.
+    Link {
+        #[schemars(with = "IndexSchema")]
+        premise: Index,
+        #[schemars(with = "IndexSchema")]
+        conclusion: Index,
+        #[serde(default)]
+        group: usize,
+    },
+    /// removes `premise` from a premise group of `conclusion`, or from all of them if `group` is not given.
+    Unlink {
+        #[schemars(with = "IndexSchema")]
+        premise: Index,
+        #[schemars(with = "IndexSchema")]
+        conclusion: Index,
+        #[serde(default)]
+        group: Option<usize>,
+    },
+    /// `force` asks the AI again even if the verdict is cached.
+    ProveDirect {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+        #[serde(default)]
+        force: bool,
+    },
+    ProveImplication {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+        #[serde(default)]
+        group: usize,
+        #[serde(default)]
+        force: bool,
+    },
+    /// `attacker` argues against a statement or the inference from a premise to its conclusion.
+    Attack {
+        #[schemars(with = "IndexSchema")]
+        attacker: Index,
+        target: AttackTarget,
+    },
+    Unattack {
+        #[schemars(with = "IndexSchema")]
+        attacker: Index,
+        target: AttackTarget,
+    },
+    ProveAttack {
+        #[schemars(with = "IndexSchema")]
+        attacker: Index,
+        target: AttackTarget,
+        #[serde(default)]
+        force: bool,
+    },
+    /// stops all checks of the AI running for the statement, its premise groups and its attacks.
+    CancelVerification {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+    },
+    Undo,
+    Redo,
 }
 
-/// handles communication between client and server.
+/// close code for clients of another protocol version, in the range reserved for applications.
+const PROTOCOL_MISMATCH: u16 = 4000;
+
+/// identifies one websocket connection within a game.
+pub type ClientId = usize;
+
+/// handles communication between the server and all clients connected to one game.
+#[derive(Default)]
 pub struct Messenger {
-    pub sender: SplitSink<WebSocket, Message>,
+    clients: HashMap<ClientId, UnboundedSender<Message>>,
+    next_client_id: ClientId,
+    /// the request being handled, if its client gave it an id. Replies to that client carry the id.
+    request: Option<(ClientId, RequestId)>,
+    /// clients whose `Hello` was accepted, only they may send other requests.
+    greeted: HashSet<ClientId>,
 }
 
 impl Messenger {
-    async fn send(&mut self, msg: ServerMessage) {
-        let _ = self
-            .sender
-            .send(Message::Text(serde_json::to_string(&msg).unwrap()))
-            .await;
+    fn connect(&mut self, sender: UnboundedSender<Message>) -> ClientId {
+        let id = self.next_client_id;
+        self.next_client_id += 1;
+        self.clients.insert(id, sender);
+        id
     }
-    async fn send_cooldown(&mut self, seconds: u64) {
-        let _ = self.send(ServerMessage::AICooldown { seconds }).await;
+    fn disconnect(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+        self.greeted.remove(&client);
     }
-    async fn send_tree(&mut self, tree: &TreeState) {
-        //push game state to client(s)
-        let tree_dto: TreeStateDTO = tree.as_dto();
-        let _ = self.send(ServerMessage::GameState(tree_dto)).await;
+    fn clients(&self) -> Vec<ClientId> { self.clients.keys().copied().collect() }
+    fn encode(msg: &ServerMessage) -> Message { Message::Text(serde_json::to_string(msg).unwrap()) }
+    /// broadcast to all clients of this game.
+    fn send(&mut self, msg: ServerMessage) {
+        let msg = Self::encode(&msg);
+        // clients whose socket task is gone are dropped here, before they get to disconnect themselves.
+        self.clients.retain(|_, sender| sender.send(msg.clone()).is_ok());
     }
-    async fn msg(&mut self, id: Index, comment: String, success: bool) {
+    fn send_diff(&mut self, tree: &TreeState) {
+        //push the changes of the latest revision to all clients
+        let diff = tree
+            .diff_since(tree.revision() - 1)
+            .expect("latest revision is remembered");
+        self.send(ServerMessage::GameDiff(diff));
+    }
+    fn msg(&mut self, id: Index, comment: String, success: bool) {
         //append message to node
-        let _ = self.send(ServerMessage::Comment { id, comment, success }).await;
+        self.send(ServerMessage::Comment { id, comment, success });
     }
-    async fn msg_win(&mut self) {
-        let _ = self.send(ServerMessage::Win).await;
+    fn msg_win(&mut self) { self.send(ServerMessage::Win); }
+    /// end the connection of a client, it gets no more messages.
+    fn close(&mut self, client: ClientId, reason: &str) {
+        self.greeted.remove(&client);
+        if let Some(sender) = self.clients.remove(&client) {
+            let _ = sender.send(Message::Close(Some(CloseFrame {
+                code: PROTOCOL_MISMATCH,
+                reason: reason.to_string().into(),
+            })));
+        }
     }
-    /* Methods to (in future) only reply to the client that triggered some command */
-    async fn reply(&mut self, msg: ServerMessage) {
-        self.send(msg).await;
+    /* Methods to only reply to the client that triggered some command */
+    fn reply(&mut self, client: ClientId, msg: ServerMessage) {
+        let Some(sender) = self.clients.get(&client) else {
+            return;
+        };
+        let msg = match &self.request {
+            Some((requester, request_id)) if *requester == client => Message::Text(
+                serde_json::to_string(&ServerReply {
+                    request_id,
+                    message: &msg,
+                })
+                .unwrap(),
+            ),
+            _ => Self::encode(&msg),
+        };
+        let _ = sender.send(msg);
     }
-    async fn reply_tree(&mut self, tree: &TreeState) {
+    fn reply_tree(&mut self, client: ClientId, tree: &TreeState) {
         let tree_dto: TreeStateDTO = tree.as_dto();
-        let _ = self.reply(ServerMessage::GameState(tree_dto)).await;
+        self.reply(client, ServerMessage::GameState(tree_dto));
     }
 }
 
 pub struct GameState {
+    id: GameId,
     tree: TreeState,
-    ai: AI,
+    ai: AI<Provider>,
     messenger: Messenger,
+    storage: Arc<Storage>,
+    /// AI comments, oldest first. Also kept here, so clients that connect do not wait for the storage.
+    comments: Vec<StoredComment>,
+    limits: Limits,
+    /// the game itself, for checks of the AI to report back to. Set by `shared`.
+    this: Weak<Mutex<GameState>>,
+    checks: HashMap<u64, RunningCheck>,
+    next_check: u64,
+}
+
+/// a question the AI is answering in the background.
+struct RunningCheck {
+    client: ClientId,
+    id: Index,
+    kind: VerificationKind,
+    question: Question,
+    /// the id of the `Prove...` that started the check, to acknowledge it once the verdict cache was looked up.
+    request: Option<RequestId>,
+    task: JoinHandle<()>,
 }
 
 impl GameState {
-    pub fn new(root_statement: String, messenger: Messenger) -> Self {
-        Self {
-            tree: TreeState::new(root_statement),
-            ai: AI {
-                cooldown_until: Instant::now(),
-                max_ai_cooldown_seconds: env::var("MAX_AI_COOLDOWN_SECONDS")
-                    .expect("MAX_AI_COOLDOWN_SECONDS not in env")
-                    .parse::<u64>()
-                    .expect("MAX_AI_COOLDOWN_SECONDS must be a number."),
+    pub fn new(id: GameId, root_statement: String, storage: Arc<Storage>, ai: AI<Provider>, limits: Limits) -> Self {
+        Self::with_tree(id, TreeState::new(root_statement), storage, ai, limits)
+    }
+
+    /// a new game from an argument map of another tool.
+    pub fn import(
+        id: GameId,
+        format: ImportFormat,
+        text: &str,
+        storage: Arc<Storage>,
+        ai: AI<Provider>,
+        limits: Limits,
+    ) -> Result<Self, ImportError> {
+        let tree = TreeState::import(format, text)?;
+        check_lengths(&tree, limits)?;
+        Ok(Self::with_tree(id, tree, storage, ai, limits))
+    }
+
+    /// a new game from a document downloaded with `saved`, including its comments.
+    pub fn from_saved(
+        id: GameId,
+        text: &str,
+        storage: Arc<Storage>,
+        ai: AI<Provider>,
+        limits: Limits,
+    ) -> Result<Self, LoadError> {
+        let saved = SavedGame::load(text)?;
+        check_lengths(&saved.game, limits).map_err(LoadError::Invalid)?;
+        let mut game = Self::with_tree(id, saved.game, storage, ai, limits);
+        for comment in &saved.comments {
+            game.storage.add_comment(id, comment);
+        }
+        game.comments = saved.comments;
+        Ok(game)
+    }
+
+    fn with_tree(id: GameId, tree: TreeState, storage: Arc<Storage>, ai: AI<Provider>, limits: Limits) -> Self {
+        let game = Self {
+            id,
+            tree,
+            ai,
+            messenger: Messenger::default(),
+            storage,
+            comments: Vec::new(),
+            limits,
+            this: Weak::new(),
+            checks: HashMap::new(),
+            next_check: 0,
+        };
+        game.save();
+        game
+    }
+
+    /// restore a game from storage, if it exists there.
+    pub async fn load(id: GameId, storage: Arc<Storage>, ai: AI<Provider>, limits: Limits) -> Option<Self> {
+        let tree = match storage.load_tree(id).await {
+            Ok(snapshot) => SavedGame::load(&snapshot?),
+            Err(e) => {
+                tracing::error!("could not load game {}: {}", id, e);
+                return None;
+            }
+        };
+        let tree = match tree {
+            Ok(saved) => saved.game,
+            Err(e) => {
+                tracing::error!("could not read game {}: {}", id, e);
+                return None;
+            }
+        };
+        let comments = storage.comments(id).await.unwrap_or_else(|e| {
+            tracing::error!("could not load comments of game {}: {}", id, e);
+            Vec::new()
+        });
+        Some(Self {
+            id,
+            tree,
+            ai,
+            messenger: Messenger::default(),
+            storage,
+            comments,
+            limits,
+            this: Weak::new(),
+            checks: HashMap::new(),
+            next_check: 0,
+        })
+    }
+
+    pub fn limits(&self) -> Limits { self.limits }
+
+    /// answer a message of `client` that could not be read, with its request id if that could be read.
+    pub fn reject(&mut self, client: ClientId, request_id: Option<RequestId>, error: ProtocolError) {
+        self.messenger.request = request_id.map(|request_id| (client, request_id));
+        self.messenger.reply(client, ServerMessage::ProtocolError(error));
+        self.messenger.request = None;
+    }
+
+    /// the game, shared by its connections and the checks of the AI running for it.
+    pub fn shared(mut self) -> Arc<Mutex<Self>> {
+        Arc::new_cyclic(|this| {
+            self.this = this.clone();
+            Mutex::new(self)
+        })
+    }
+
+    /// register a new client connection. Messages for it are pushed into `sender`.
+    /// The client is sent all stored comments, so it knows why nodes are in their current state.
+    pub fn connect(&mut self, sender: UnboundedSender<Message>) -> ClientId {
+        let client = self.messenger.connect(sender);
+        self.messenger.reply(
+            client,
+            ServerMessage::Welcome {
+                protocol: PROTOCOL_VERSION,
             },
-            messenger,
+        );
+        for StoredComment { id, comment, success } in self.comments.clone() {
+            if self.tree.get_statement(id).is_ok() {
+                self.messenger
+                    .reply(client, ServerMessage::Comment { id, comment, success });
+            }
         }
+        client
+    }
+
+    pub fn export(&self, format: ExportFormat) -> String { self.tree.export(format) }
+
+    /// the complete game as versioned JSON document, with all comments.
+    pub fn saved(&self) -> String {
+        let saved = SavedGame::new(&self.tree, self.comments.clone());
+        serde_json::to_string_pretty(&saved).expect("game must be serializable")
     }
 
-    /// handle incoming messages from client(s). Returns a message to be sent only to the sender.
-    pub async fn on_incoming_message(&mut self, incoming_message: ClientMessage) {
-        //remember if we want to push the tree (as long as no error happens)
-        let state_change = &mut match incoming_message {
-            Add { .. } | Delete { .. } | Link { .. } | Unlink { .. } | Edit { .. } => true,
-            _ => false,
+    /// `false` once the server closed the connection of the client, its socket should end then.
+    pub fn connected(&self, client: ClientId) -> bool { self.messenger.clients.contains_key(&client) }
+
+    pub fn disconnect(&mut self, client: ClientId) {
+        self.messenger.disconnect(client);
+        self.ai.disconnect(client);
+    }
+
+    /// handle incoming messages from client(s). Errors and new ids are only sent to `client`.
+    /// Requests with an id are answered with `Ack` if they succeed without any other result.
+    /// Checks of the AI are only started here, so other messages can be handled while the AI thinks.
+    pub fn on_incoming_message(&mut self, client: ClientId, request: ClientRequest) {
+        if !self.connected(client) {
+            return;
+        }
+        let incoming_message = match request {
+            ClientRequest::Tagged { request_id, message } => {
+                self.messenger.request = Some((client, request_id));
+                message
+            }
+            ClientRequest::Bare(message) => message,
         };
+        if let Hello { protocol } = incoming_message {
+            self.hello(client, protocol);
+            self.messenger.request = None;
+            return;
+        }
+        if !self.messenger.greeted.contains(&client) {
+            let error = ProtocolError::HelloRequired;
+            self.messenger.reply(client, ServerMessage::ProtocolError(error));
+            self.messenger.request = None;
+            return;
+        }
+        let has_result = matches!(
+            incoming_message,
+            Add { .. } | GetGameState | GetChanges { .. } | GetNodeHistory { .. }
+        );
+        // a `Prove...` the AI is not asked for is refused with a comment instead.
+        let mut asked = true;
 
         //handle incoming messages from client(s)
         let result: Result<(), ProofError> = match incoming_message {
-            Add { statement } => {
+            Add { statement } => check_length(&statement, self.limits).map(|()| {
                 let id = self.tree.add_node(statement);
-                self.messenger.reply(ServerMessage::NewNodeId(id)).await;
+                self.messenger.reply(client, ServerMessage::NewNodeId(id));
+            }),
+            GetGameState => {
+                self.messenger.reply_tree(client, &self.tree);
                 Ok(())
             }
-            GetGameState => {
-                self.messenger.reply_tree(&self.tree).await;
+            GetChanges { since } => {
+                match self.tree.diff_since(since) {
+                    Some(diff) => self.messenger.reply(client, ServerMessage::GameDiff(diff)),
+                    None => self.messenger.reply_tree(client, &self.tree),
+                }
                 Ok(())
             }
-            Link { premise, conclusion } => self.tree.link(conclusion, premise),
-            Unlink { premise, conclusion } => self.tree.unlink(conclusion, premise),
+            GetNodeHistory { id } => self.tree.get_verifications(id).map(|verifications| {
+                let verifications = verifications.to_vec();
+                self.messenger
+                    .reply(client, ServerMessage::NodeHistory { id, verifications });
+            }),
+            Link {
+                premise,
+                conclusion,
+                group,
+            } => self.tree.link(conclusion, premise, group),
+            Unlink {
+                premise,
+                conclusion,
+                group,
+            } => self.tree.unlink(conclusion, premise, group),
             Delete { id } => self.tree.remove_node(id),
-            Edit { id, statement } => self.tree.change_node_statement(id, statement),
-            ProveDirect { id } => self.prove_direct(id, state_change).await,
-            ProveImplication { id } => self.prove_implication(id, state_change).await,
+            Edit { id, statement } => {
+                check_length(&statement, self.limits).and_then(|()| self.tree.change_node_statement(id, statement))
+            }
+            ProveDirect { id, force } => self
+                .verify(client, id, VerificationKind::Direct, force)
+                .map(|verified| asked = verified),
+            ProveImplication { id, group, force } => self
+                .verify(client, id, VerificationKind::Implication { group }, force)
+                .map(|verified| asked = verified),
+            Attack { attacker, target } => self.tree.attack(attacker, target),
+            Unattack { attacker, target } => self.tree.unattack(attacker, target),
+            ProveAttack {
+                attacker,
+                target,
+                force,
+            } => match self.tree.get_statement(target.affected()).map(str::to_string) {
+                Ok(attacked) => self
+                    .verify(client, attacker, VerificationKind::Attack { target, attacked }, force)
+                    .map(|verified| asked = verified),
+                Err(e) => Err(e),
+            },
+            CancelVerification { id } => self.cancel(id),
+            Hello { .. } => unreachable!("handled above"),
+            Undo => self.tree.undo(),
+            Redo => self.tree.redo(),
         };
-        if let Err(e) = result {
-            self.messenger.reply(ServerMessage::Error(e)).await;
-        } else {
-            if *state_change {
-                self.messenger.send_tree(&self.tree).await;
-                if self.tree.proof_complete() {
-                    self.messenger.msg_win().await;
-                }
+        let succeeded = match result {
+            Ok(()) => true,
+            Err(e) => {
+                self.messenger.reply(client, ServerMessage::Error(e));
+                false
             }
+        };
+        self.publish();
+        if succeeded && asked && !has_result && self.messenger.request.is_some() {
+            self.messenger.reply(client, ServerMessage::Ack);
         }
+        self.messenger.request = None;
     }
 
-    pub async fn prove_direct(&mut self, id: Index, tree_changed: &mut bool) -> Result<(), ProofError> {
-        self.messenger.send_cooldown(self.ai.max_ai_cooldown_seconds).await;
-        match self.ai.check_statement(self.tree.get_statement(id)?).await {
-            Ok(explanation) => {
-                self.tree.set_directly_proven(id);
-                *tree_changed = true;
-                self.messenger.msg(id, explanation, true).await;
-            }
-            Err(explanation) => {
-                self.messenger.msg(id, explanation, false).await;
+    /// check the protocol version of a client. Clients of another version are told why and disconnected.
+    fn hello(&mut self, client: ClientId, protocol: u32) {
+        if protocol != PROTOCOL_VERSION {
+            let error = ProtocolError::UnsupportedVersion {
+                client: protocol,
+                server: PROTOCOL_VERSION,
+            };
+            let reason = error.to_string();
+            self.messenger.reply(client, ServerMessage::ProtocolError(error));
+            self.messenger.close(client, &reason);
+            self.ai.disconnect(client);
+            return;
+        }
+        self.messenger.greeted.insert(client);
+        if self.messenger.request.is_some() {
+            self.messenger.reply(client, ServerMessage::Ack);
+        }
+    }
+
+    /// tell all clients what changed since the last revision, if anything did.
+    fn publish(&mut self) {
+        if self.tree.commit_revision() {
+            self.save();
+            self.messenger.send_diff(&self.tree);
+            if self.tree.proof_complete() {
+                self.messenger.msg_win();
             }
         }
-        Ok(())
     }
-    pub async fn prove_implication(&mut self, id: Index, tree_changed: &mut bool) -> Result<(), ProofError> {
-        self.messenger.send_cooldown(self.ai.max_ai_cooldown_seconds).await;
-        let conclusion = self.tree.get_statement(id)?;
-        let premises = self.tree.get_premises(id)?;
-        if premises.len() == 0 {
-            self.messenger
-                .msg(
+
+    /// what to ask the AI for a check of `kind` on the statement `id`, as the tree is now.
+    fn question(&self, id: Index, kind: &VerificationKind) -> Result<Question, ProofError> {
+        let statement = self.tree.get_statement(id)?.to_string();
+        Ok(match kind {
+            VerificationKind::Direct => Question::Statement(statement),
+            VerificationKind::Implication { group } => Question::Implication {
+                premises: self
+                    .tree
+                    .get_premises(id, *group)?
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                conclusion: statement,
+            },
+            VerificationKind::Attack { target, .. } => {
+                self.tree.get_attack(id, *target)?;
+                let premise = match *target {
+                    AttackTarget::Node(_) => None,
+                    AttackTarget::Link { premise, .. } => Some(self.tree.get_statement(premise)?.to_string()),
+                };
+                Question::Attack {
+                    attacker: statement,
+                    target: self.tree.get_statement(target.affected())?.to_string(),
+                    premise,
+                }
+            }
+        })
+    }
+
+    /// check the statement `id` in the background: apply the cached verdict or ask the AI, or with `force`
+    /// ask the AI right away. Asking again while the same question is being answered does nothing.
+    /// `false` if the request is not to be acknowledged now: the client was told why the AI is not asked,
+    /// or the verdict cache is looked up first, see `on_lookup`.
+    fn verify(&mut self, client: ClientId, id: Index, kind: VerificationKind, force: bool) -> Result<bool, ProofError> {
+        let question = self.question(id, &kind)?;
+        if matches!(&question, Question::Implication { premises, .. } if premises.is_empty()) {
+            let comment = "You need to add at least one premise to prove an implication.".to_string();
+            self.messenger.reply(
+                client,
+                ServerMessage::Comment {
                     id,
-                    "You need to add at least one premise to prove an implication.".to_string(),
-                    false,
-                )
-                .await;
-            return Ok(());
+                    comment,
+                    success: false,
+                },
+            );
+            return Ok(false);
+        }
+        if self
+            .checks
+            .values()
+            .any(|check| check.id == id && check.question == question)
+        {
+            return Ok(true);
         }
-        match self.ai.check_implication(&premises, conclusion).await {
-            Ok(explanation) => {
-                self.tree.set_implied(id);
-                *tree_changed = true;
-                self.messenger.msg(id, explanation, true).await;
+        if !force {
+            let request = match &self.messenger.request {
+                Some((requester, request_id)) if *requester == client => Some(request_id.clone()),
+                _ => None,
+            };
+            self.start(client, id, kind, question, request, false);
+            return Ok(false);
+        }
+        let allowed = self.ai.check_cooldown(client);
+        self.send_cooldowns();
+        if let Err(failure) = allowed {
+            self.fail(client, id, failure);
+            return Ok(false);
+        }
+        self.start(client, id, kind, question, None, true);
+        Ok(true)
+    }
+
+    /// check `question` without holding the game: look it up in the verdict cache, or let the AI answer
+    /// if `ask` is set. The statement is `verifying` until the check ends.
+    fn start(
+        &mut self,
+        client: ClientId,
+        id: Index,
+        kind: VerificationKind,
+        question: Question,
+        request: Option<RequestId>,
+        ask: bool,
+    ) {
+        let check = self.next_check;
+        self.next_check += 1;
+        self.tree.start_verifying(id);
+        let task = if ask {
+            self.spawn_answer(check, &question)
+        } else {
+            self.spawn_lookup(check, &question)
+        };
+        self.checks.insert(
+            check,
+            RunningCheck {
+                client,
+                id,
+                kind,
+                question,
+                request,
+                task,
+            },
+        );
+    }
+
+    fn spawn_lookup(&self, check: u64, question: &Question) -> JoinHandle<()> {
+        let cached = self.ai.cached(question.cache_key(self.ai.model()));
+        let game = self.this.clone();
+        tokio::spawn(async move {
+            let verdict = cached.await;
+            if let Some(game) = game.upgrade() {
+                game.lock().await.on_lookup(check, verdict);
+            }
+        })
+    }
+
+    fn spawn_answer(&self, check: u64, question: &Question) -> JoinHandle<()> {
+        let answer = self.ai.ask(question.clone());
+        let game = self.this.clone();
+        tokio::spawn(async move {
+            let result = answer.await;
+            if let Some(game) = game.upgrade() {
+                game.lock().await.on_answer(check, result);
+            }
+        })
+    }
+
+    /// the verdict cache was looked up for a check started by `start`. A cached verdict is applied like an answer
+    /// of the AI, otherwise the AI is asked if the client may. The `Prove...` that started the check is
+    /// acknowledged, unless the AI is on cooldown.
+    fn on_lookup(&mut self, check: u64, cached: Option<Verdict>) {
+        // cancelled checks are gone already.
+        let Some(RunningCheck {
+            client,
+            id,
+            question,
+            request,
+            ..
+        }) = self.checks.get(&check)
+        else {
+            return;
+        };
+        let (client, id, question) = (*client, *id, question.clone());
+        self.messenger.request = request.clone().map(|request_id| (client, request_id));
+        match cached {
+            Some(verdict) => self.on_answer(check, Ok(verdict)),
+            None => {
+                let allowed = self.ai.check_cooldown(client);
+                self.send_cooldowns();
+                if let Err(failure) = allowed {
+                    self.checks.remove(&check);
+                    self.tree.stop_verifying(id);
+                    self.fail(client, id, failure);
+                    self.publish();
+                    self.messenger.request = None;
+                    return;
+                }
+                let task = self.spawn_answer(check, &question);
+                self.checks.get_mut(&check).unwrap().task = task;
             }
-            Err(explanation) => {
-                self.messenger.msg(id, explanation, false).await;
+        }
+        if self.messenger.request.is_some() {
+            self.messenger.reply(client, ServerMessage::Ack);
+        }
+        self.messenger.request = None;
+    }
+
+    /// the AI answered a check started by `start`. Its verdict is discarded if the question would be
+    /// different now, because the statement, the premises or the attack changed meanwhile.
+    fn on_answer(&mut self, check: u64, result: Result<Verdict, AiFailure>) {
+        // cancelled checks are gone already.
+        let Some(RunningCheck {
+            client,
+            id,
+            kind,
+            question,
+            ..
+        }) = self.checks.remove(&check)
+        else {
+            return;
+        };
+        self.tree.stop_verifying(id);
+        match result {
+            Ok(verdict) if self.question(id, &kind).is_ok_and(|current| current == question) => {
+                if let Err(e) = self.apply(id, kind, &question, verdict) {
+                    tracing::error!("could not apply verdict in game {}: {}", self.id, e);
+                }
             }
+            Ok(_) => self.messenger.msg(
+                id,
+                "The AI was asked before the last changes, its verdict was discarded.".to_string(),
+                false,
+            ),
+            Err(failure) => self.fail(client, id, failure),
+        }
+        self.publish();
+    }
+
+    /// stop all checks of the AI running for the statement `id`. Their verdicts are never applied.
+    fn cancel(&mut self, id: Index) -> Result<(), ProofError> {
+        let cancelled: Vec<u64> = self
+            .checks
+            .iter()
+            .filter(|(_, check)| check.id == id)
+            .map(|(&check, _)| check)
+            .collect();
+        if cancelled.is_empty() {
+            return Err(ProofError::NotVerifying(id));
+        }
+        for check in cancelled {
+            self.checks.remove(&check).unwrap().task.abort();
+            self.tree.stop_verifying(id);
         }
         Ok(())
     }
+
+    /// keep the verdict of the AI on the statement `id` and change its proof state accordingly.
+    fn apply(
+        &mut self,
+        id: Index,
+        kind: VerificationKind,
+        question: &Question,
+        verdict: Verdict,
+    ) -> Result<(), ProofError> {
+        let verification = question.verification(kind.clone(), self.ai.model(), &verdict);
+        self.tree.add_verification(id, verification)?;
+        match (kind, verdict.verdict) {
+            (VerificationKind::Direct, true) => self.tree.set_directly_proven(id),
+            (VerificationKind::Direct, false) => self.tree.set_directly_refuted(id),
+            (VerificationKind::Implication { group }, true) => self.tree.set_implied(id, group),
+            (VerificationKind::Implication { group }, false) => self.tree.set_implication_rejected(id, group),
+            (VerificationKind::Attack { target, .. }, valid) => self.tree.set_attack_valid(id, target, valid)?,
+        }
+        self.comment(id, verdict.to_string(), verdict.verdict);
+        Ok(())
+    }
+
+    /// broadcast an AI comment on a node and keep it for clients that connect later.
+    fn comment(&mut self, id: Index, comment: String, success: bool) {
+        let stored = StoredComment { id, comment, success };
+        self.storage.add_comment(self.id, &stored);
+        self.messenger.msg(id, stored.comment.clone(), success);
+        self.comments.push(stored);
+    }
+
+    /// the AI could not be asked. Not kept as a comment, as it says nothing about the statement.
+    fn fail(&mut self, client: ClientId, id: Index, failure: AiFailure) {
+        let comment = failure.to_string();
+        match failure {
+            AiFailure::Cooldown(_) => self.messenger.reply(
+                client,
+                ServerMessage::Comment {
+                    id,
+                    comment,
+                    success: false,
+                },
+            ),
+            AiFailure::Provider(_) | AiFailure::Malformed(_) => self.messenger.msg(id, comment, false),
+        }
+    }
+
+    /// tell every client how long it has to wait before it can ask the AI again.
+    fn send_cooldowns(&mut self) {
+        for client in self.messenger.clients() {
+            let seconds = self.ai.wait_time(client).as_secs_f64().ceil() as u64;
+            self.messenger.reply(client, ServerMessage::AICooldown { seconds });
+        }
+    }
+
+    fn save(&self) { self.storage.save_tree(self.id, &SavedGame::new(&self.tree, Vec::new())); }
+}
+
+/// statements may have at most `statement_length` characters.
+pub fn check_length(statement: &str, limits: Limits) -> Result<(), ProofError> {
+    let length = statement.chars().count();
+    let limit = limits.statement_length;
+    if length > limit {
+        return Err(ProofError::StatementTooLong { length, limit });
+    }
+    Ok(())
+}
+
+/// every statement of an uploaded game keeps to `statement_length`.
+fn check_lengths(tree: &TreeState, limits: Limits) -> Result<(), ProofError> {
+    tree.statements()
+        .try_for_each(|statement| check_length(statement, limits))
 }
 
-struct AI {
-    cooldown_until: Instant,
-    max_ai_cooldown_seconds: u64,
+/// why the AI could not give a verdict.
+enum AiFailure {
+    Cooldown(u64),
+    Provider(String),
+    /// the answer was neither the requested JSON object nor started with '[TRUE]'/'[FALSE]'.
+    Malformed(String),
 }
 
-const SYSTEM_MESSAGE_DIRECT: &str = "The User will give you a statement. Begin your answer with '[TRUE]', if you consider the statement to be objectively correct. If not, begin your answer with '[FALSE]' and then provide an explanation.\n
-Important:\n
-- Always use this format for your answer.\n
-- Explain very briefly but exact, in one sentence.";
-const SYSTEM_MESSAGE_IMPLICATION: &str = "The User will give you a list of assumptions and a statement. Begin your answer with '[TRUE]', if you consider the statement to be a logical consequence of the assumptions. If not, begin your answer with '[FALSE]' and tell why (f.ex. which assumptions are missing).\n
-Important:\n
-- Always use this format for your answer.\n
-- Explain very briefly but exact, in one sentence.";
-const ÎMPLICATION_PRE: &str = "Assume, the following assumptions would all be true:\n";
-const IMPLICATION_MID: &str = "Now, under this assumption, evaluate if the following statement is a consequence:\n";
-impl AI {
-    fn check_cooldown(&mut self) -> Result<(), String> {
-        if self.cooldown_until > Instant::now() {
-            return Err(format!(
-                "AI is on cooldown for the next {} second(s).",
-                self.cooldown_until.duration_since(Instant::now()).as_secs()
-            ));
+impl Display for AiFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AiFailure::Cooldown(seconds) => write!(f, "AI is on cooldown for the next {} second(s).", seconds),
+            AiFailure::Provider(e) => write!(
+                f,
+                "Server: Internal Error while consulting AI - maybe no more money? :( - {}",
+                e
+            ),
+            AiFailure::Malformed(answer) => write!(f, "Server: Could not understand the answer of the AI: {}", answer),
         }
-        //set cooldown for the next 15 seconds.
-        self.cooldown_until = Instant::now() + std::time::Duration::from_secs(self.max_ai_cooldown_seconds);
+    }
+}
+
+/// asks the AI on behalf of one game, within the rate limits of the provider, the game and each client.
+/// Verdicts are cached in the storage, so the same question is only paid for once.
+pub struct AI<A: AiApi> {
+    shared: Arc<SharedAi<A>>,
+    storage: Arc<Storage>,
+    game_limit: TokenBucket,
+    client_quota: u32,
+    client_limits: HashMap<ClientId, TokenBucket>,
+}
 
+impl<A: AiApi> AI<A> {
+    pub fn new(shared: Arc<SharedAi<A>>, quota: AiQuota, storage: Arc<Storage>) -> Self {
+        Self {
+            shared,
+            storage,
+            game_limit: TokenBucket::per_minute(quota.game),
+            client_quota: quota.client,
+            client_limits: HashMap::new(),
+        }
+    }
+    fn disconnect(&mut self, client: ClientId) { self.client_limits.remove(&client); }
+    /// time until `client` may ask the AI again.
+    fn wait_time(&mut self, client: ClientId) -> Duration {
+        let provider_wait_time = self
+            .shared
+            .members
+            .iter()
+            .map(|member| member.limit.lock().unwrap().wait_time_for(self.shared.samples))
+            .max()
+            .unwrap_or_default();
+        let client_limit = self
+            .client_limits
+            .entry(client)
+            .or_insert_with(|| TokenBucket::per_minute(self.client_quota));
+        provider_wait_time
+            .max(self.game_limit.wait_time())
+            .max(client_limit.wait_time())
+    }
+    fn check_cooldown(&mut self, client: ClientId) -> Result<(), AiFailure> {
+        let wait_time = self.wait_time(client);
+        if !wait_time.is_zero() {
+            return Err(AiFailure::Cooldown(wait_time.as_secs_f64().ceil() as u64));
+        }
+        for member in &self.shared.members {
+            member.limit.lock().unwrap().take_for(self.shared.samples);
+        }
+        self.game_limit.take();
+        self.client_limits.get_mut(&client).unwrap().take();
         Ok(())
     }
-    fn parse_ai_result(&mut self, ai_result: Result<String, reqwest::Error>) -> Result<String, String> {
-        let result = match ai_result {
-            Ok(msg) => msg,
-            Err(e) => {
-                return Err(format!(
-                    "Server: Internal Error while consulting AI - maybe no more money? :( - {:?}",
-                    e
-                ));
+    fn parse_ai_result(ai_result: Result<String, A::AiError>) -> Result<Verdict, AiFailure> {
+        let answer = ai_result.map_err(|e| AiFailure::Provider(e.to_string()))?;
+        Verdict::parse(&answer).ok_or(AiFailure::Malformed(answer))
+    }
+    /// ask every provider `samples` times at once and combine the answers.
+    /// Also says whether every answer was usable, a verdict reached without some of them is not final.
+    async fn consult(shared: &SharedAi<A>, question: &Question) -> (Result<Verdict, AiFailure>, bool) {
+        let requests = shared
+            .members
+            .iter()
+            .flat_map(|member| (0..shared.samples).map(move |_| &member.api))
+            .map(|api| async move { (api.model(), Self::parse_ai_result(question.ask(api).await)) });
+        let answers = join_all(requests).await;
+        let complete = answers.iter().all(|(_, answer)| answer.is_ok());
+        (consensus(answers, shared.quorum), complete)
+    }
+    /// the verdict given on the same question before. The lookup does not borrow the game.
+    fn cached(&self, key: CacheKey) -> impl Future<Output = Option<Verdict>> + 'static {
+        let storage = self.storage.clone();
+        async move {
+            match storage.cached_verdict(&key).await {
+                Ok(verdict) => verdict.map(|verdict| Verdict {
+                    cached: true,
+                    ..verdict
+                }),
+                Err(e) => {
+                    tracing::error!("could not read verdict cache: {}", e);
+                    None
+                }
+            }
+        }
+    }
+    fn remember(storage: &Storage, key: &CacheKey, result: Result<Verdict, AiFailure>) -> Result<Verdict, AiFailure> {
+        if let Ok(verdict) = &result {
+            storage.cache_verdict(key, verdict);
+        }
+        result
+    }
+    /// the model, or models of a consensus, answering for this game.
+    fn model(&self) -> String { self.shared.model() }
+}
+
+impl<A: AiApi + 'static> AI<A> {
+    /// ask the AI once `check_cooldown` allowed it. The answer does not borrow the game,
+    /// so it can be awaited in the background.
+    fn ask(&self, question: Question) -> impl Future<Output = Result<Verdict, AiFailure>> + 'static {
+        let (shared, storage) = (self.shared.clone(), self.storage.clone());
+        async move {
+            let (result, complete) = Self::consult(&shared, &question).await;
+            if !complete || !shared.cacheable() {
+                return result;
+            }
+            Self::remember(&storage, &question.cache_key(shared.model()), result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::ai::mock::Mock;
+
+    fn mock_ai(quota: AiQuota, storage: Arc<Storage>) -> AI<Provider> {
+        let shared = Arc::new(SharedAi::new(vec![Provider::Mock(Mock::default())], 1, None));
+        AI::new(shared, quota, storage)
+    }
+
+    fn game(quota: AiQuota) -> GameState {
+        let storage = Arc::new(Storage::in_memory().unwrap());
+        let ai = mock_ai(quota, storage.clone());
+        GameState::new(
+            GameId::new(),
+            "Socrates is mortal.".into(),
+            storage,
+            ai,
+            Limits::default(),
+        )
+    }
+
+    /// a new client of `game`, and a function returning what it received since it was last called.
+    fn connect(game: &mut GameState) -> (ClientId, impl FnMut() -> Vec<Value>) {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let client = game.connect(sender);
+        request(game, client, r#"{"Hello":{"protocol":1}}"#);
+        let received = move || {
+            let mut messages = Vec::new();
+            while let Ok(Message::Text(text)) = receiver.try_recv() {
+                messages.push(serde_json::from_str(&text).unwrap());
             }
+            messages
         };
-        if result.starts_with("[TRUE]") {
-            //todo: reset cooldown if true, and emit message to client
-            //self.cooldown_until = Instant::now();
-            Ok(result[6..].to_string())
-        } else if result.starts_with("[FALSE]") {
-            Err(result[7..].to_string())
-        } else {
-            Err(result.to_string())
+        (client, received)
+    }
+
+    fn request(game: &mut GameState, client: ClientId, text: &str) {
+        game.on_incoming_message(client, ClientRequest::parse(text, 1000).unwrap());
+    }
+
+    #[tokio::test]
+    async fn uploads_keep_to_the_statement_length() {
+        let mut game = game(AiQuota::default());
+        let root = game.tree.as_dto().root;
+        let man = game.tree.add_node("Socrates is a man, like Plato.".into());
+        game.tree.link(root, man, 0).unwrap();
+        let storage = game.storage.clone();
+        let limits = Limits {
+            statement_length: 20,
+            ..Limits::default()
+        };
+
+        let argdown = game.tree.to_argdown();
+        let ai = mock_ai(AiQuota::default(), storage.clone());
+        let imported = GameState::import(
+            GameId::new(),
+            ImportFormat::Argdown,
+            &argdown,
+            storage.clone(),
+            ai,
+            limits,
+        );
+        assert!(matches!(
+            imported,
+            Err(ImportError::Proof(ProofError::StatementTooLong {
+                length: 30,
+                limit: 20
+            }))
+        ));
+        let ai = mock_ai(AiQuota::default(), storage.clone());
+        let loaded = GameState::from_saved(GameId::new(), &game.saved(), storage, ai, limits);
+        assert!(matches!(
+            loaded,
+            Err(LoadError::Invalid(ProofError::StatementTooLong {
+                length: 30,
+                limit: 20
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn ignores_clients_without_accepted_hello() {
+        let mut game = game(AiQuota::default());
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let client = game.connect(sender);
+        let add = r#"{"Add":{"statement":"Socrates is a man."}}"#;
+        request(&mut game, client, add);
+        request(&mut game, client, r#"{"Hello":{"protocol":0}}"#);
+        request(&mut game, client, r#"{"Hello":{"protocol":1}}"#);
+        request(&mut game, client, add);
+        assert_eq!(game.tree.as_dto().statements.len(), 1, "the Add is ignored");
+        assert!(!game.connected(client));
+
+        let mut messages = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            messages.push(message);
         }
+        let text = |message: &Message| match message {
+            Message::Text(text) => serde_json::from_str::<Value>(text).unwrap(),
+            message => panic!("unexpected {:?}", message),
+        };
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert_eq!(text(&messages[1]), json!({"ProtocolError": "HelloRequired"}));
+        assert!(text(&messages[2])["ProtocolError"]["UnsupportedVersion"].is_object());
+        assert!(matches!(&messages[3], Message::Close(Some(frame)) if frame.code == PROTOCOL_MISMATCH));
     }
-    async fn check_statement(&mut self, statement: &str) -> Result<String, String> {
-        self.check_cooldown()?;
 
-        self.parse_ai_result(openai::request(SYSTEM_MESSAGE_DIRECT.to_string(), statement.to_string()).await)
+    fn ack(request_id: u64) -> Value { json!({"request_id": request_id, "message": "Ack"}) }
+
+    #[tokio::test]
+    async fn replies_carry_the_request_id() {
+        let mut game = game(AiQuota::default());
+        let (client, mut received) = connect(&mut game);
+        assert_eq!(received(), vec![json!({"Welcome": {"protocol": PROTOCOL_VERSION}})]);
+
+        request(&mut game, client, r#"{"request_id":"a","message":"GetGameState"}"#);
+        let replies = received();
+        assert_eq!(replies.len(), 1, "results are no Ack: {:?}", replies);
+        assert_eq!(replies[0]["request_id"], "a");
+        assert!(replies[0]["message"]["GameState"].is_object());
+        request(&mut game, client, r#""GetGameState""#);
+        assert!(received()[0]["GameState"].is_object(), "bare requests get bare replies");
+    }
+
+    /// what `received` gets until a message matches `until`, while the checks of the AI run.
+    async fn receive_until(received: &mut impl FnMut() -> Vec<Value>, until: impl Fn(&Value) -> bool) -> Vec<Value> {
+        let mut messages = Vec::new();
+        for _ in 0..200 {
+            messages.extend(received());
+            if messages.iter().any(&until) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        messages
     }
-    async fn check_implication(&mut self, premises: &[&str], conclusion: &str) -> Result<String, String> {
-        self.check_cooldown()?;
-        let user_message = format!(
-            "{}{}{}{}",
-            ÎMPLICATION_PRE,
-            premises.join("\n"),
-            IMPLICATION_MID,
-            conclusion
+
+    #[tokio::test]
+    async fn acknowledges_only_successful_commands() {
+        let game = game(AiQuota { game: 10, client: 1 }).shared();
+        let (client, mut received) = connect(&mut *game.lock().await);
+        let (_, mut other) = connect(&mut *game.lock().await);
+        received();
+        other();
+
+        request(
+            &mut *game.lock().await,
+            client,
+            r#"{"request_id":1,"message":{"Add":{"statement":"Socrates is a man."}}}"#,
+        );
+        let replies = received();
+        assert_eq!(replies[0], json!({"request_id": 1, "message": {"NewNodeId": [1, 0]}}));
+        assert!(!replies.contains(&ack(1)));
+
+        let link = r#"{"request_id":2,"message":{"Link":{"premise":[1,0],"conclusion":[0,0],"group":0}}}"#;
+        request(&mut *game.lock().await, client, link);
+        let replies = received();
+        assert!(replies[0]["GameDiff"].is_object(), "broadcasts are not wrapped");
+        assert_eq!(replies.last(), Some(&ack(2)));
+        assert!(!other().contains(&ack(2)), "only the requesting client is acknowledged");
+
+        request(
+            &mut *game.lock().await,
+            client,
+            r#"{"request_id":3,"message":{"Delete":{"id":[0,0]}}}"#,
+        );
+        assert_eq!(
+            received(),
+            vec![json!({"request_id": 3, "message": {"Error": "RemoveRoot"}})]
+        );
+
+        request(
+            &mut *game.lock().await,
+            client,
+            r#"{"request_id":4,"message":{"ProveDirect":{"id":[1,0]}}}"#,
+        );
+        assert!(!received().contains(&ack(4)), "the verdict cache is looked up first");
+        let replies = receive_until(&mut received, |reply| *reply == ack(4)).await;
+        assert!(replies.contains(&ack(4)), "the AI was asked: {:?}", replies);
+        request(
+            &mut *game.lock().await,
+            client,
+            r#"{"request_id":5,"message":{"ProveDirect":{"id":[0,0]}}}"#,
+        );
+        let refused = |reply: &Value| reply["request_id"] == 5 && reply["message"]["Comment"]["success"] == false;
+        let replies = receive_until(&mut received, refused).await;
+        assert!(replies.iter().any(refused));
+        assert!(!replies.contains(&ack(5)), "the AI is on cooldown: {:?}", replies);
+    }
+
+    #[tokio::test]
+    async fn applies_cached_verdicts_without_holding_the_game() {
+        let game = game(AiQuota { game: 10, client: 1 });
+        let question = Question::Statement("Socrates is mortal.".into());
+        let verdict = Verdict::new(true, "Socrates is a man.");
+        game.storage
+            .cache_verdict(&question.cache_key(game.ai.model()), &verdict);
+        let game = game.shared();
+        let (client, mut received) = connect(&mut *game.lock().await);
+        received();
+
+        let prove = r#"{"request_id":1,"message":{"ProveDirect":{"id":[0,0]}}}"#;
+        request(&mut *game.lock().await, client, prove);
+        let replies = receive_until(&mut received, |reply| *reply == ack(1)).await;
+        assert!(game.lock().await.tree.proof_complete());
+        assert_eq!(replies.last(), Some(&ack(1)), "{:?}", replies);
+        assert!(replies.contains(&json!("Win")), "the verdict is applied before the Ack");
+
+        request(
+            &mut *game.lock().await,
+            client,
+            r#"{"request_id":2,"message":{"Add":{"statement":"Socrates is a man."}}}"#,
+        );
+        let prove = r#"{"request_id":3,"message":{"ProveDirect":{"id":[1,0]}}}"#;
+        request(&mut *game.lock().await, client, prove);
+        let replies = receive_until(&mut received, |reply| *reply == ack(3)).await;
+        assert!(replies.contains(&ack(3)), "the cached verdict was free: {:?}", replies);
+    }
+
+    /// answers every question the same way, or fails if there is no verdict.
+    struct Fixed(Option<bool>);
+
+    impl AiApi for Fixed {
+        type AiError = String;
+
+        fn rate_limit(&self) -> u32 { 0 }
+        fn model(&self) -> String { "fixed".into() }
+
+        async fn request(&self, _system_message: &str, _input: &str) -> Result<String, Self::AiError> {
+            let verdict = self.0.ok_or("unavailable")?;
+            Ok(serde_json::to_string(&Verdict::new(verdict, "Fixed answer.")).unwrap())
+        }
+    }
+
+    async fn cached_after_asking<A: AiApi + 'static>(apis: Vec<A>) -> Option<Verdict> {
+        let storage = Arc::new(Storage::in_memory().unwrap());
+        let ai = AI::new(
+            Arc::new(SharedAi::new(apis, 1, Some(1))),
+            AiQuota::default(),
+            storage.clone(),
+        );
+        let question = Question::Statement("Socrates is mortal.".into());
+        assert!(ai.ask(question.clone()).await.is_ok());
+        storage.cached_verdict(&question.cache_key(ai.model())).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn caches_only_final_verdicts() {
+        assert!(cached_after_asking(vec![Fixed(Some(true)), Fixed(Some(false))])
+            .await
+            .is_some());
+        assert!(
+            cached_after_asking(vec![Fixed(Some(true)), Fixed(None)])
+                .await
+                .is_none(),
+            "another answer might have changed the verdict"
         );
-        self.parse_ai_result(openai::request(SYSTEM_MESSAGE_IMPLICATION.to_string(), user_message).await)
+        assert!(cached_after_asking(vec![Mock::default()]).await.is_none());
     }
 }
@@ -0,0 +1,340 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use generational_arena::Index;
+use serde::Deserialize;
+
+use super::proof::{AttackTarget, ProofState, TreeState};
+use super::{StatementDTO, TreeStateDTO};
+
+/// text formats a game can be exported to, for pasting finished arguments into documents.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// GraphViz
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+    /// indented outline, starting at the root
+    Markdown,
+    /// Argument Interchange Format, as JSON in the layout of AIFdb
+    Aif,
+    Argdown,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Dot => "text/vnd.graphviz; charset=utf-8",
+            ExportFormat::Mermaid => "text/plain; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Aif => "application/json",
+            ExportFormat::Argdown => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+impl TreeState {
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Dot => self.to_dot(),
+            ExportFormat::Mermaid => self.to_mermaid(),
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Aif => self.to_aif(),
+            ExportFormat::Argdown => self.to_argdown(),
+        }
+    }
+
+    /// premises point to their conclusions, attacks are red edges ending in a bar.
+    /// Dashed edges are links and attacks the AI has not accepted.
+    pub fn to_dot(&self) -> String {
+        let tree = self.as_dto();
+        let mut dot =
+            String::from("digraph argument {\n    rankdir=BT;\n    node [shape=box, style=\"rounded,filled\"];\n");
+        for node in &tree.statements {
+            let (label, color) = status(node);
+            let text = format!("{}\n({})", node.statement, label);
+            let root = if node.id == tree.root { ", penwidth=2" } else { "" };
+            let _ = writeln!(
+                dot,
+                "    {} [label=\"{}\", fillcolor=\"{}\"{}];",
+                node_name(node.id),
+                escape_dot(&text),
+                color.light(),
+                root
+            );
+        }
+        for (conclusion, group, premise, accepted) in links(&tree) {
+            let label = edge_label(&tree, conclusion, group);
+            let style = if accepted { "solid" } else { "dashed" };
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [style={}{}];",
+                node_name(premise),
+                node_name(conclusion),
+                style,
+                label.map_or(String::new(), |label| format!(", label=\"{}\"", label))
+            );
+        }
+        for attack in &tree.attacks {
+            let style = if attack.valid { "solid" } else { "dashed" };
+            let _ = writeln!(
+                dot,
+                "    {} -> {} [color=red, arrowhead=tee, style={}{}];",
+                node_name(attack.attacker),
+                node_name(attacked(attack.target)),
+                style,
+                attack_label(attack.target).map_or(String::new(), |label| format!(", label=\"{}\"", label))
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// same layout as `to_dot`, attacks end in a cross.
+    pub fn to_mermaid(&self) -> String {
+        let tree = self.as_dto();
+        let mut mermaid = String::from("flowchart BT\n");
+        for node in &tree.statements {
+            let (label, color) = status(node);
+            let text = format!("{}<br/>({})", escape_mermaid(&node.statement), label);
+            let _ = writeln!(mermaid, "    {}[\"{}\"]", node_name(node.id), text);
+            let _ = writeln!(mermaid, "    class {} {}", node_name(node.id), color.class());
+        }
+        for (conclusion, group, premise, accepted) in links(&tree) {
+            let arrow = if accepted { "-->" } else { "-.->" };
+            let label = edge_label(&tree, conclusion, group).map_or(String::new(), |label| format!("|{}|", label));
+            let _ = writeln!(
+                mermaid,
+                "    {} {}{} {}",
+                node_name(premise),
+                arrow,
+                label,
+                node_name(conclusion)
+            );
+        }
+        for attack in &tree.attacks {
+            let arrow = if attack.valid { "--x" } else { "-.-x" };
+            let label = attack_label(attack.target).map_or(String::new(), |label| format!("|{}|", label));
+            let _ = writeln!(
+                mermaid,
+                "    {} {}{} {}",
+                node_name(attack.attacker),
+                arrow,
+                label,
+                node_name(attacked(attack.target))
+            );
+        }
+        for color in [Color::Holds, Color::Open, Color::Refuted] {
+            let _ = writeln!(
+                mermaid,
+                "    classDef {} fill:{},stroke:#333",
+                color.class(),
+                color.light()
+            );
+        }
+        mermaid
+    }
+
+    /// the root with its premises nested below it, followed by the statements attacking each.
+    /// Statements that appear more than once are only expanded the first time.
+    pub fn to_markdown(&self) -> String {
+        let tree = self.as_dto();
+        let nodes: HashMap<Index, &StatementDTO> = tree.statements.iter().map(|node| (node.id, node)).collect();
+        let mut markdown = String::new();
+        let mut expanded = HashSet::new();
+        outline(&tree, &nodes, tree.root, 0, "", &mut expanded, &mut markdown);
+        markdown
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Color {
+    Holds,
+    Open,
+    Refuted,
+}
+
+impl Color {
+    fn light(&self) -> &'static str {
+        match self {
+            Color::Holds => "#c8f7c5",
+            Color::Open => "#fff3b0",
+            Color::Refuted => "#f7c5c5",
+        }
+    }
+
+    fn class(&self) -> &'static str {
+        match self {
+            Color::Holds => "holds",
+            Color::Open => "open",
+            Color::Refuted => "refuted",
+        }
+    }
+}
+
+/// human readable state of a statement, and whether it holds, is refuted or still open.
+fn status(node: &StatementDTO) -> (String, Color) {
+    let (label, mut color) = match node.state {
+        ProofState::DirectlyProven => ("proven directly", Color::Holds),
+        ProofState::ImpliedProven => ("implied, proven", Color::Holds),
+        ProofState::ImpliedUnproven => ("implied, unproven", Color::Open),
+        ProofState::None => ("open", Color::Open),
+        ProofState::DirectlyRefuted => ("refuted", Color::Refuted),
+        ProofState::ImplicationRejected => ("implication rejected", Color::Refuted),
+    };
+    let mut label = label.to_string();
+    if node.defeated {
+        label.push_str(", defeated");
+        color = Color::Refuted;
+    }
+    if node.unsupported {
+        label.push_str(", unsupported");
+        color = Color::Refuted;
+    }
+    (label, color)
+}
+
+fn node_name(id: Index) -> String {
+    let (index, generation) = id.into_raw_parts();
+    format!("n{}_{}", index, generation)
+}
+
+/// every link as (conclusion, group, premise, whether the AI accepted the group).
+fn links(tree: &TreeStateDTO) -> Vec<(Index, usize, Index, bool)> {
+    let mut links = Vec::new();
+    for node in &tree.statements {
+        for (group, premises) in node.groups.iter().enumerate() {
+            for &premise in &premises.premises {
+                links.push((node.id, group, premise, premises.accepted && !premises.stale));
+            }
+        }
+    }
+    links
+}
+
+/// groups are only labeled if there is more than one way to prove the conclusion.
+fn edge_label(tree: &TreeStateDTO, conclusion: Index, group: usize) -> Option<String> {
+    let node = tree.statements.iter().find(|node| node.id == conclusion)?;
+    (node.groups.len() > 1).then(|| format!("group {}", group))
+}
+
+/// the node an attack edge ends at: the attacked statement, or the conclusion of the attacked inference.
+fn attacked(target: AttackTarget) -> Index {
+    match target {
+        AttackTarget::Node(id) => id,
+        AttackTarget::Link { conclusion, .. } => conclusion,
+    }
+}
+
+fn attack_label(target: AttackTarget) -> Option<String> {
+    match target {
+        AttackTarget::Node(_) => None,
+        AttackTarget::Link { premise, .. } => Some(format!("inference from {}", node_name(premise))),
+    }
+}
+
+fn escape_dot(text: &str) -> String { text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n") }
+
+fn escape_mermaid(text: &str) -> String { text.replace('"', "#quot;").replace('\n', "<br/>") }
+
+fn outline(
+    tree: &TreeStateDTO,
+    nodes: &HashMap<Index, &StatementDTO>,
+    id: Index,
+    depth: usize,
+    prefix: &str,
+    expanded: &mut HashSet<Index>,
+    markdown: &mut String,
+) {
+    let Some(node) = nodes.get(&id) else {
+        return;
+    };
+    let indent = "  ".repeat(depth);
+    let statement = node.statement.replace('\n', " ");
+    let (label, _) = status(node);
+    if !expanded.insert(id) {
+        let _ = writeln!(markdown, "{}- {}{} _({}, see above)_", indent, prefix, statement, label);
+        return;
+    }
+    let _ = writeln!(markdown, "{}- {}**{}** _({})_", indent, prefix, statement, label);
+    let several = node.groups.len() > 1;
+    for (group, premises) in node.groups.iter().enumerate() {
+        let mut depth = depth + 1;
+        if several {
+            let verdict = if premises.stale {
+                "stale"
+            } else if premises.accepted {
+                "accepted"
+            } else if premises.rejected {
+                "rejected"
+            } else {
+                "unchecked"
+            };
+            let _ = writeln!(markdown, "{}  - group {} ({}):", indent, group, verdict);
+            depth += 1;
+        }
+        for &premise in &premises.premises {
+            outline(tree, nodes, premise, depth, "", expanded, markdown);
+        }
+    }
+    for attack in tree.attacks.iter().filter(|attack| attacked(attack.target) == id) {
+        let prefix = match attack.target {
+            AttackTarget::Node(_) if attack.valid => "attacked by: ".to_string(),
+            AttackTarget::Node(_) => "attacked (unchecked) by: ".to_string(),
+            AttackTarget::Link { premise, .. } => format!(
+                "inference from \"{}\" attacked{} by: ",
+                nodes.get(&premise).map_or("", |premise| &premise.statement),
+                if attack.valid { "" } else { " (unchecked)" }
+            ),
+        };
+        outline(tree, nodes, attack.attacker, depth + 1, &prefix, expanded, markdown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// root implied by a directly proven premise, which is attacked.
+    fn argument() -> (TreeState, Index, Index, Index) {
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let root = tree.as_dto().root;
+        let premise = tree.add_node("Every \"man\" is mortal.".into());
+        tree.link(root, premise, 0).unwrap();
+        tree.set_directly_proven(premise);
+        tree.set_implied(root, 0);
+        let attacker = tree.add_node("Some men lived forever.".into());
+        tree.attack(attacker, AttackTarget::Node(premise)).unwrap();
+        (tree, root, premise, attacker)
+    }
+
+    #[test]
+    fn dot_and_mermaid_contain_every_edge() {
+        let (tree, root, premise, attacker) = argument();
+        let dot = tree.to_dot();
+        assert!(dot.contains(r##"label="Every \"man\" is mortal.\n(proven directly)", fillcolor="#c8f7c5""##));
+        assert!(dot.contains(&format!("{} -> {} [style=solid];", node_name(premise), node_name(root))));
+        assert!(dot.contains(&format!(
+            "{} -> {} [color=red, arrowhead=tee, style=dashed];",
+            node_name(attacker),
+            node_name(premise)
+        )));
+
+        let mermaid = tree.to_mermaid();
+        assert!(mermaid.contains(&format!("{} --> {}", node_name(premise), node_name(root))));
+        assert!(mermaid.contains(&format!("{} -.-x {}", node_name(attacker), node_name(premise))));
+        assert!(mermaid.contains("Every #quot;man#quot; is mortal."));
+    }
+
+    #[test]
+    fn markdown_outline_nests_premises_and_attacks() {
+        let (tree, ..) = argument();
+        assert_eq!(
+            tree.to_markdown(),
+            "- **Socrates is mortal.** _(implied, proven)_\n  \
+             - **Every \"man\" is mortal.** _(proven directly)_\n    \
+             - attacked (unchecked) by: **Some men lived forever.** _(open)_\n"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+use generational_arena::Index;
+use serde::{Deserialize, Serialize};
+
+use super::proof::{Attack, ProofState};
+
+/// how many operations can be undone at most.
+const MAX_UNDO: usize = 100;
+
+/// one elementary, invertible modification of a `TreeState`.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum Change {
+    Add(Index),
+    Remove(Index),
+    Statement {
+        id: Index,
+        old: String,
+        new: String,
+    },
+    /// an empty premise group appended to `id`.
+    AddGroup(Index),
+    /// `child_pos` is the position of `child` in the premises of the `group` of `parent`,
+    /// `parent_pos` that of `parent` in `child.parents`.
+    Link {
+        parent: Index,
+        child: Index,
+        #[serde(default)]
+        group: usize,
+        child_pos: usize,
+        parent_pos: usize,
+    },
+    Unlink {
+        parent: Index,
+        child: Index,
+        #[serde(default)]
+        group: usize,
+        child_pos: usize,
+        parent_pos: usize,
+    },
+    GroupAccepted {
+        id: Index,
+        group: usize,
+        old: bool,
+        new: bool,
+    },
+    GroupRejected {
+        id: Index,
+        group: usize,
+        old: bool,
+        new: bool,
+    },
+    GroupVerified {
+        id: Index,
+        group: usize,
+        old: Option<u64>,
+        new: Option<u64>,
+    },
+    State {
+        id: Index,
+        old: ProofState,
+        new: ProofState,
+    },
+    /// `pos` is the position of the attack in `TreeState::attacks`.
+    AddAttack {
+        pos: usize,
+        attack: Attack,
+    },
+    RemoveAttack {
+        pos: usize,
+        attack: Attack,
+    },
+    AttackValid {
+        pos: usize,
+        old: bool,
+        new: bool,
+    },
+}
+
+/// everything one command changed, including proof states that trickled up.
+pub type Operation = Vec<Change>;
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct History {
+    undo: VecDeque<Operation>,
+    redo: Vec<Operation>,
+    #[serde(skip)]
+    pending: Operation,
+}
+
+impl History {
+    pub fn record(&mut self, change: Change) { self.pending.push(change); }
+
+    /// finish the current operation. A new operation discards everything that could be redone.
+    pub fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let operation = std::mem::take(&mut self.pending);
+        self.push_undo(operation);
+        self.redo.clear();
+    }
+
+    /// drop the current operation instead of keeping it for undo.
+    pub fn discard(&mut self) -> Operation { std::mem::take(&mut self.pending) }
+
+    /// every change that can be undone or redone.
+    pub fn changes(&self) -> impl Iterator<Item = &Change> { self.undo.iter().chain(&self.redo).flatten() }
+
+    pub fn take_undo(&mut self) -> Option<Operation> { self.undo.pop_back() }
+
+    pub fn take_redo(&mut self) -> Option<Operation> { self.redo.pop() }
+
+    pub fn push_undo(&mut self, operation: Operation) {
+        self.undo.push_back(operation);
+        if self.undo.len() > MAX_UNDO {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn push_redo(&mut self, operation: Operation) { self.redo.push(operation); }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use generational_arena::Index;
+use serde::Deserialize;
+
+use super::proof::{AttackTarget, ProofError, TreeState};
+
+mod aif;
+mod argdown;
+
+/// formats of other argument mapping tools a game can be imported from, and exported to.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Argument Interchange Format, as JSON in the layout of AIFdb
+    Aif,
+    Argdown,
+}
+
+/// why an argument map could not be imported.
+#[derive(Debug)]
+pub enum ImportError {
+    Json(serde_json::Error),
+    /// the Argdown line, starting at 1, that could not be read.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// a relation refers to a statement or inference that is not part of the map.
+    UnknownNode(String),
+    /// the map contains no statement to become the root.
+    Empty,
+    /// the map is not a valid game, e.g. because of circular premises.
+    Proof(ProofError),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Json(e) => write!(f, "Invalid AIF JSON: {}", e),
+            ImportError::Syntax { line, message } => write!(f, "Line {}: {}", line, message),
+            ImportError::UnknownNode(node) => write!(f, "Unknown node {}", node),
+            ImportError::Empty => write!(f, "The argument map contains no statement."),
+            ImportError::Proof(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ProofError> for ImportError {
+    fn from(e: ProofError) -> Self { ImportError::Proof(e) }
+}
+
+/// what the interchange formats have in common: statements, premise groups and attacks, without verdicts.
+/// Statements are referred to by position, the first one is the root.
+#[derive(Default, PartialEq, Debug)]
+struct ArgumentMap {
+    statements: Vec<String>,
+    /// one entry per premise group: conclusion and premises.
+    inferences: Vec<(usize, Vec<usize>)>,
+    attacks: Vec<(usize, MapTarget)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MapTarget {
+    Statement(usize),
+    /// the inference from `premise`, in the inference at the given position of `ArgumentMap::inferences`.
+    Inference {
+        inference: usize,
+        premise: usize,
+    },
+}
+
+impl ArgumentMap {
+    fn from_tree(tree: &TreeState) -> Self {
+        let dto = tree.as_dto();
+        let mut nodes = dto.statements.iter().collect::<Vec<_>>();
+        // the root goes first, the others keep the order they were added in.
+        nodes.sort_by_key(|node| node.id != dto.root);
+        let position: HashMap<Index, usize> = nodes.iter().enumerate().map(|(pos, node)| (node.id, pos)).collect();
+        let mut map = ArgumentMap {
+            statements: nodes.iter().map(|node| node.statement.clone()).collect(),
+            ..Default::default()
+        };
+        for node in &nodes {
+            for group in node.groups.iter().filter(|group| !group.premises.is_empty()) {
+                let premises = group.premises.iter().map(|premise| position[premise]).collect();
+                map.inferences.push((position[&node.id], premises));
+            }
+        }
+        for attack in &dto.attacks {
+            let target = match attack.target {
+                AttackTarget::Node(id) => MapTarget::Statement(position[&id]),
+                AttackTarget::Link { premise, conclusion } => {
+                    let (conclusion, premise) = (position[&conclusion], position[&premise]);
+                    let inference = map
+                        .inferences
+                        .iter()
+                        .position(|(target, premises)| *target == conclusion && premises.contains(&premise))
+                        .expect("attacked links exist");
+                    MapTarget::Inference { inference, premise }
+                }
+            };
+            map.attacks.push((position[&attack.attacker], target));
+        }
+        map
+    }
+
+    /// a new game of the map. It starts without verdicts and without undo history.
+    fn into_tree(self) -> Result<TreeState, ImportError> {
+        let mut statements = self.statements.into_iter();
+        let mut tree = TreeState::new(statements.next().ok_or(ImportError::Empty)?);
+        let mut ids = vec![tree.as_dto().root];
+        ids.extend(statements.map(|statement| tree.add_node(statement)));
+        let mut groups = HashMap::<usize, usize>::new();
+        for (conclusion, premises) in &self.inferences {
+            let group = groups.entry(*conclusion).or_default();
+            for &premise in premises {
+                tree.link(ids[*conclusion], ids[premise], *group)?;
+            }
+            *group += 1;
+        }
+        for (attacker, target) in self.attacks {
+            let target = match target {
+                MapTarget::Statement(target) => AttackTarget::Node(ids[target]),
+                MapTarget::Inference { inference, premise } => AttackTarget::Link {
+                    premise: ids[premise],
+                    conclusion: ids[self.inferences[inference].0],
+                },
+            };
+            match tree.attack(ids[attacker], target) {
+                // undercutting several inferences of the same premise is one attack here.
+                Ok(()) | Err(ProofError::AddExistingAttack { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        tree.forget_history();
+        Ok(tree)
+    }
+}
+
+impl TreeState {
+    /// a new game from an argument map of another tool. The first statement of the map becomes the root.
+    pub fn import(format: ImportFormat, text: &str) -> Result<Self, ImportError> {
+        let map = match format {
+            ImportFormat::Aif => aif::parse(text)?,
+            ImportFormat::Argdown => argdown::parse(text)?,
+        };
+        map.into_tree()
+    }
+
+    pub fn to_aif(&self) -> String { aif::write(&ArgumentMap::from_tree(self)) }
+
+    pub fn to_argdown(&self) -> String { argdown::write(&ArgumentMap::from_tree(self)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// two ways to prove the root, an attack on a statement and one on an inference.
+    fn argument() -> TreeState {
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let root = tree.as_dto().root;
+        let man = tree.add_node("Socrates is a man.".into());
+        let mortal = tree.add_node("Every man is mortal.".into());
+        let hemlock = tree.add_node("Socrates drank hemlock.".into());
+        let immortal = tree.add_node("Some men lived forever.".into());
+        let dose = tree.add_node("The dose was too small.".into());
+        tree.link(root, man, 0).unwrap();
+        tree.link(root, mortal, 0).unwrap();
+        tree.link(root, hemlock, 1).unwrap();
+        tree.attack(immortal, AttackTarget::Node(mortal)).unwrap();
+        tree.attack(
+            dose,
+            AttackTarget::Link {
+                premise: hemlock,
+                conclusion: root,
+            },
+        )
+        .unwrap();
+        tree
+    }
+
+    #[test]
+    fn aif_round_trip() {
+        let tree = argument();
+        let imported = TreeState::import(ImportFormat::Aif, &tree.to_aif()).unwrap();
+        assert_eq!(ArgumentMap::from_tree(&imported), ArgumentMap::from_tree(&tree));
+        assert_eq!(imported.to_aif(), tree.to_aif());
+    }
+
+    #[test]
+    fn argdown_round_trip() {
+        let tree = argument();
+        let imported = TreeState::import(ImportFormat::Argdown, &tree.to_argdown()).unwrap();
+        assert_eq!(ArgumentMap::from_tree(&imported), ArgumentMap::from_tree(&tree));
+        assert_eq!(imported.to_argdown(), tree.to_argdown());
+    }
+
+    #[test]
+    fn argdown_keeps_attacked_premise_and_annotations() {
+        let mut tree = TreeState::new("Socrates is mortal. #classic {source: Plato}".into());
+        let root = tree.as_dto().root;
+        let man = tree.add_node("Socrates is a man #1".into());
+        let mortal = tree.add_node("Every man is mortal, see C:\\notes\\{men}.".into());
+        let god = tree.add_node("Socrates was a god.".into());
+        tree.link(root, man, 0).unwrap();
+        tree.link(root, mortal, 0).unwrap();
+        tree.attack(
+            god,
+            AttackTarget::Link {
+                premise: man,
+                conclusion: root,
+            },
+        )
+        .unwrap();
+        let argdown = tree.to_argdown();
+        assert!(argdown.contains("_> <a1> {premise: s2}"), "{}", argdown);
+
+        let imported = TreeState::import(ImportFormat::Argdown, &argdown).unwrap();
+        assert_eq!(ArgumentMap::from_tree(&imported), ArgumentMap::from_tree(&tree));
+        let text = "<a>\n\n(1) [p]: P\n(2) [q]: Q\n----\n(3) [c]: C\n\n[u]: U\n  _> <a> {premise: q}\n";
+        let map = argdown::parse(text).unwrap();
+        assert_eq!(
+            map.attacks,
+            vec![(
+                3,
+                MapTarget::Inference {
+                    inference: 0,
+                    premise: 1
+                }
+            )]
+        );
+        assert!(argdown::parse(&text.replace("premise: q", "premise: c")).is_err());
+    }
+
+    #[test]
+    fn rejects_circular_maps() {
+        let text = "[a]: A\n  + [b]: B\n    + [a]\n";
+        assert!(matches!(
+            TreeState::import(ImportFormat::Argdown, text),
+            Err(ImportError::Proof(ProofError::CircularLink { .. }))
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::{ArgumentMap, ImportError, MapTarget};
+
+/// an argument map in the JSON layout of AIFdb. Statements are I-nodes, premise groups RA-nodes
+/// and attacks CA-nodes. Other node types, like locutions, are ignored.
+#[derive(Serialize, Deserialize)]
+struct Aif {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    #[serde(default)]
+    locutions: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Node {
+    #[serde(rename = "nodeID", deserialize_with = "id")]
+    id: String,
+    #[serde(default)]
+    text: String,
+    #[serde(rename = "type")]
+    kind: String,
+    /// not part of AIF: the premise whose inference a CA-node attacks, if it points to an RA-node.
+    /// Without it, the inferences from all premises of the RA-node are attacked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    premise: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Edge {
+    #[serde(rename = "edgeID", deserialize_with = "id")]
+    id: String,
+    #[serde(rename = "fromID", deserialize_with = "id")]
+    from: String,
+    #[serde(rename = "toID", deserialize_with = "id")]
+    to: String,
+}
+
+/// AIFdb uses strings as ids, some tools numbers.
+fn id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Text(String),
+        Number(u64),
+    }
+    Ok(match Id::deserialize(deserializer)? {
+        Id::Text(id) => id,
+        Id::Number(id) => id.to_string(),
+    })
+}
+
+pub(super) fn parse(text: &str) -> Result<ArgumentMap, ImportError> {
+    let aif: Aif = serde_json::from_str(text).map_err(ImportError::Json)?;
+    let kinds: HashMap<&str, &str> = aif.nodes.iter().map(|node| (&*node.id, &*node.kind)).collect();
+    let mut incoming = HashMap::<&str, Vec<&str>>::new();
+    let mut outgoing = HashMap::<&str, Vec<&str>>::new();
+    for edge in &aif.edges {
+        for end in [&edge.from, &edge.to] {
+            if !kinds.contains_key(&**end) {
+                return Err(ImportError::UnknownNode(end.clone()));
+            }
+        }
+        incoming.entry(&edge.to).or_default().push(&edge.from);
+        outgoing.entry(&edge.from).or_default().push(&edge.to);
+    }
+    let statements: Vec<&Node> = aif.nodes.iter().filter(|node| node.kind == "I").collect();
+    let position: HashMap<&str, usize> = statements
+        .iter()
+        .enumerate()
+        .map(|(pos, node)| (&*node.id, pos))
+        .collect();
+    let statements_of = |ids: Option<&Vec<&str>>| -> Vec<usize> {
+        ids.into_iter()
+            .flatten()
+            .filter_map(|id| position.get(id).copied())
+            .collect()
+    };
+
+    let mut map = ArgumentMap {
+        statements: statements.iter().map(|node| node.text.clone()).collect(),
+        ..Default::default()
+    };
+    // positions in `map.inferences` of each RA-node, one per conclusion.
+    let mut inferences = HashMap::<&str, Vec<usize>>::new();
+    for node in aif.nodes.iter().filter(|node| node.kind == "RA") {
+        let premises = statements_of(incoming.get(&*node.id));
+        if premises.is_empty() {
+            continue;
+        }
+        for conclusion in statements_of(outgoing.get(&*node.id)) {
+            inferences.entry(&node.id).or_default().push(map.inferences.len());
+            map.inferences.push((conclusion, premises.clone()));
+        }
+    }
+    for node in aif.nodes.iter().filter(|node| node.kind == "CA") {
+        let premise = match &node.premise {
+            Some(premise) => Some(
+                *position
+                    .get(&**premise)
+                    .ok_or_else(|| ImportError::UnknownNode(premise.clone()))?,
+            ),
+            None => None,
+        };
+        for attacker in statements_of(incoming.get(&*node.id)) {
+            for &target in outgoing.get(&*node.id).into_iter().flatten() {
+                if let Some(&target) = position.get(target) {
+                    map.attacks.push((attacker, MapTarget::Statement(target)));
+                }
+                for &inference in inferences.get(target).into_iter().flatten() {
+                    let premises = &map.inferences[inference].1;
+                    for &attacked in premises
+                        .iter()
+                        .filter(|&&attacked| premise.is_none_or(|p| p == attacked))
+                    {
+                        map.attacks.push((
+                            attacker,
+                            MapTarget::Inference {
+                                inference,
+                                premise: attacked,
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(map)
+}
+
+pub(super) fn write(map: &ArgumentMap) -> String {
+    let mut aif = Aif {
+        nodes: Vec::new(),
+        edges: Vec::new(),
+        locutions: Vec::new(),
+    };
+    let add_node = |aif: &mut Aif, kind: &str, text: &str, premise: Option<String>| -> String {
+        let id = (aif.nodes.len() + 1).to_string();
+        aif.nodes.push(Node {
+            id: id.clone(),
+            text: text.to_string(),
+            kind: kind.to_string(),
+            premise,
+        });
+        id
+    };
+    let add_edge = |aif: &mut Aif, from: &str, to: &str| {
+        aif.edges.push(Edge {
+            id: (aif.edges.len() + 1).to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    };
+
+    let statements: Vec<String> = map
+        .statements
+        .iter()
+        .map(|statement| add_node(&mut aif, "I", statement, None))
+        .collect();
+    let mut inferences = Vec::new();
+    for (conclusion, premises) in &map.inferences {
+        let inference = add_node(&mut aif, "RA", "Default Inference", None);
+        for &premise in premises {
+            add_edge(&mut aif, &statements[premise], &inference);
+        }
+        add_edge(&mut aif, &inference, &statements[*conclusion]);
+        inferences.push(inference);
+    }
+    for &(attacker, target) in &map.attacks {
+        let (target, premise) = match target {
+            MapTarget::Statement(target) => (&statements[target], None),
+            MapTarget::Inference { inference, premise } => (&inferences[inference], Some(statements[premise].clone())),
+        };
+        let conflict = add_node(&mut aif, "CA", "Default Conflict", premise);
+        add_edge(&mut aif, &statements[attacker], &conflict);
+        add_edge(&mut aif, &conflict, target);
+    }
+    serde_json::to_string_pretty(&aif).expect("AIF is serializable")
+}
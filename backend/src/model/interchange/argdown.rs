@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::{ArgumentMap, ImportError, MapTarget};
+
+/// a statement or an argument, as written in a line.
+#[derive(Clone, PartialEq, Debug)]
+enum Element {
+    Statement(usize),
+    Argument(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Relation {
+    Support,
+    Attack,
+    Undercut,
+}
+
+/// relation prefixes, incoming ones point from the element of the line to its parent.
+const RELATIONS: [(&str, Relation, bool); 8] = [
+    ("<+", Relation::Support, true),
+    ("<-", Relation::Attack, true),
+    ("<_", Relation::Undercut, true),
+    ("+>", Relation::Support, false),
+    ("->", Relation::Attack, false),
+    ("_>", Relation::Undercut, false),
+    ("+", Relation::Support, true),
+    ("-", Relation::Attack, true),
+];
+
+/// reads the subset of Argdown `write` produces, plus the relations between statements other tools write:
+/// statements `[title]: text`, nested relations `+`, `-`, `<+`, `<-`, `+>`, `->`, `_>` and `<_`,
+/// and arguments `<title>` with premise-conclusion structures. Undercuts attack the inferences from all
+/// premises of the last inference of an argument, or only from the premise titled in data `{premise: title}`.
+/// Other tags, data and comments are skipped, `\` escapes the next character of a statement.
+pub(super) fn parse(text: &str) -> Result<ArgumentMap, ImportError> {
+    let mut parser = Parser::default();
+    for (line, content) in text.lines().enumerate() {
+        parser.line = line + 1;
+        parser.parse_line(content)?;
+    }
+    parser.finish()
+}
+
+#[derive(Default)]
+struct Parser {
+    line: usize,
+    map: ArgumentMap,
+    titles: HashMap<String, usize>,
+    /// positions in `map.inferences` of each argument with a premise-conclusion structure.
+    arguments: HashMap<String, Vec<usize>>,
+    /// elements of the lines above the current one with their indentation, for nested relations.
+    parents: Vec<(usize, Element)>,
+    /// the argument whose premise-conclusion structure is being read, and the premises since its last inference.
+    structure: Option<(String, Vec<usize>)>,
+    /// the next numbered statement is a conclusion.
+    after_inference: bool,
+    /// line, source, relation, target and the title of the attacked premise of an undercut argument.
+    relations: Vec<(usize, Element, Relation, Element, Option<String>)>,
+    in_front_matter: bool,
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> ImportError {
+        ImportError::Syntax {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), ImportError> {
+        let content = line.trim();
+        if content == "===" {
+            self.in_front_matter = !self.in_front_matter;
+            return Ok(());
+        }
+        if self.in_front_matter || content.is_empty() || content.starts_with("//") || content.starts_with('#') {
+            return Ok(());
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        if let Some((argument, premises)) = &mut self.structure {
+            if content.starts_with("--") {
+                if premises.is_empty() {
+                    return Err(self.error("inference without premises"));
+                }
+                self.after_inference = true;
+                return Ok(());
+            }
+            if let Some(rest) = numbered(content) {
+                let argument = argument.clone();
+                let Element::Statement(statement) = self.element(rest)? else {
+                    return Err(self.error("premises and conclusions have to be statements"));
+                };
+                if std::mem::take(&mut self.after_inference) {
+                    let (_, premises) = self.structure.as_mut().unwrap();
+                    let inference = self.map.inferences.len();
+                    self.map.inferences.push((statement, std::mem::take(premises)));
+                    self.arguments.entry(argument).or_default().push(inference);
+                }
+                // a conclusion is a premise of the next inference of the argument.
+                self.structure.as_mut().unwrap().1.push(statement);
+                return Ok(());
+            }
+        }
+
+        let relation = RELATIONS
+            .iter()
+            .find(|(prefix, ..)| content.starts_with(prefix) && !content.starts_with("--"));
+        match relation {
+            Some(&(prefix, relation, incoming)) if indent > 0 => {
+                let content = content[prefix.len()..].trim();
+                let element = self.element(content)?;
+                let premise = attacked_premise(content);
+                while self
+                    .parents
+                    .last()
+                    .is_some_and(|(parent_indent, _)| *parent_indent >= indent)
+                {
+                    self.parents.pop();
+                }
+                let Some((_, parent)) = self.parents.last().cloned() else {
+                    return Err(self.error("relation without an element it belongs to"));
+                };
+                let (from, to) = if incoming {
+                    (element.clone(), parent)
+                } else {
+                    (parent, element.clone())
+                };
+                self.relations.push((self.line, from, relation, to, premise));
+                self.parents.push((indent, element));
+            }
+            Some(_) => return Err(self.error("relations have to be indented below an element")),
+            None => {
+                let element = self.element(content)?;
+                self.structure = match &element {
+                    Element::Argument(title) => Some((title.clone(), Vec::new())),
+                    Element::Statement(_) => None,
+                };
+                self.after_inference = false;
+                self.parents = vec![(indent, element)];
+            }
+        }
+        Ok(())
+    }
+
+    /// `[title]: text`, `[title]`, `<title>: description`, `<title>` or text of an untitled statement.
+    fn element(&mut self, content: &str) -> Result<Element, ImportError> {
+        if let Some(rest) = content.strip_prefix('<') {
+            let end = rest.find('>').ok_or_else(|| self.error("missing '>'"))?;
+            return Ok(Element::Argument(rest[..end].trim().to_string()));
+        }
+        let (title, text) = match content.strip_prefix('[') {
+            Some(rest) => {
+                let end = rest.find(']').ok_or_else(|| self.error("missing ']'"))?;
+                let text = rest[end + 1..].trim_start().strip_prefix(':').map(strip_annotations);
+                (Some(rest[..end].trim().to_string()), text)
+            }
+            None => (None, Some(strip_annotations(content))),
+        };
+        let statement = match title.as_ref().and_then(|title| self.titles.get(title)) {
+            Some(&statement) => statement,
+            None => {
+                self.map.statements.push(String::new());
+                self.map.statements.len() - 1
+            }
+        };
+        if let Some(title) = title {
+            self.titles.insert(title, statement);
+        }
+        if let Some(text) = text.filter(|text| !text.is_empty()) {
+            self.map.statements[statement] = text;
+        }
+        Ok(Element::Statement(statement))
+    }
+
+    fn finish(mut self) -> Result<ArgumentMap, ImportError> {
+        // statements only referred to by title are named by it.
+        for (title, &statement) in &self.titles {
+            if self.map.statements[statement].is_empty() {
+                self.map.statements[statement] = title.clone();
+            }
+        }
+        for (line, from, relation, to, premise) in std::mem::take(&mut self.relations) {
+            self.line = line;
+            let from = self.statement(from)?;
+            match (relation, to) {
+                (Relation::Support, to) => {
+                    let to = self.statement(to)?;
+                    self.map.inferences.push((to, vec![from]));
+                }
+                (Relation::Attack, Element::Statement(to)) => self.map.attacks.push((from, MapTarget::Statement(to))),
+                (_, Element::Argument(title)) => {
+                    let &inference = self.inferences(&title)?.last().unwrap();
+                    let premises = &self.map.inferences[inference].1;
+                    let attacked = match premise {
+                        None => premises.clone(),
+                        Some(premise) => match self.titles.get(&premise) {
+                            Some(&premise) if premises.contains(&premise) => vec![premise],
+                            _ => return Err(self.error(format!("[{}] is no premise of <{}>", premise, title))),
+                        },
+                    };
+                    for premise in attacked {
+                        self.map
+                            .attacks
+                            .push((from, MapTarget::Inference { inference, premise }));
+                    }
+                }
+                (Relation::Undercut, Element::Statement(_)) => {
+                    return Err(self.error("only arguments can be undercut"));
+                }
+            }
+        }
+        Ok(self.map)
+    }
+
+    /// arguments stand for their final conclusion.
+    fn statement(&self, element: Element) -> Result<usize, ImportError> {
+        match element {
+            Element::Statement(statement) => Ok(statement),
+            Element::Argument(title) => {
+                let &inference = self.inferences(&title)?.last().unwrap();
+                Ok(self.map.inferences[inference].0)
+            }
+        }
+    }
+
+    fn inferences(&self, argument: &str) -> Result<&Vec<usize>, ImportError> {
+        self.arguments
+            .get(argument)
+            .ok_or_else(|| ImportError::UnknownNode(format!("<{}>", argument)))
+    }
+}
+
+/// the rest of a premise-conclusion line `(1) ...`.
+fn numbered(content: &str) -> Option<&str> {
+    let rest = content.strip_prefix('(')?;
+    let end = rest.find(')')?;
+    rest[..end]
+        .chars()
+        .all(|c| c.is_ascii_digit())
+        .then(|| rest[end + 1..].trim())
+}
+
+/// the title in data `{premise: title}` after an argument `<title>`.
+fn attacked_premise(content: &str) -> Option<String> {
+    let data = content.strip_prefix('<')?.split_once('>')?.1.trim();
+    let (key, title) = data.strip_prefix('{')?.strip_suffix('}')?.split_once(':')?;
+    (key.trim() == "premise").then(|| title.trim().to_string())
+}
+
+/// statement text without trailing tags `#tag` and data `{...}`, with escaped characters unescaped.
+fn strip_annotations(text: &str) -> String {
+    let text = match text.rfind(" {") {
+        Some(data) if text.trim_end().ends_with('}') => &text[..data],
+        _ => text,
+    };
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let end = words
+        .iter()
+        .rposition(|word| !word.starts_with('#'))
+        .map_or(0, |last| last + 1);
+    unescape(&words[..end].join(" "))
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        unescaped.push(if c == '\\' { chars.next().unwrap_or(c) } else { c });
+    }
+    unescaped
+}
+
+/// statement text that reads back unchanged: no line breaks, and tags and data escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '#' | '{' => escaped.extend(['\\', c]),
+            '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// statements are titled `s1`, `s2`, ... by position, premise groups become arguments `a1`, `a2`, ...
+/// Attacks on an inference undercut the argument of its premise group, naming the attacked premise if there are several.
+pub(super) fn write(map: &ArgumentMap) -> String {
+    let mut argdown = String::new();
+    for (pos, statement) in map.statements.iter().enumerate() {
+        let _ = writeln!(argdown, "[s{}]: {}\n", pos + 1, escape(statement));
+    }
+    for (pos, (conclusion, premises)) in map.inferences.iter().enumerate() {
+        let _ = writeln!(argdown, "<a{}>\n", pos + 1);
+        for (number, premise) in premises.iter().enumerate() {
+            let _ = writeln!(argdown, "({}) [s{}]", number + 1, premise + 1);
+        }
+        let _ = writeln!(argdown, "----\n({}) [s{}]\n", premises.len() + 1, conclusion + 1);
+    }
+    for &(attacker, target) in &map.attacks {
+        let target = match target {
+            MapTarget::Statement(target) => format!("-> [s{}]", target + 1),
+            MapTarget::Inference { inference, premise } if map.inferences[inference].1.len() > 1 => {
+                format!("_> <a{}> {{premise: s{}}}", inference + 1, premise + 1)
+            }
+            MapTarget::Inference { inference, .. } => format!("_> <a{}>", inference + 1),
+        };
+        let _ = writeln!(argdown, "[s{}]\n  {}\n", attacker + 1, target);
+    }
+    argdown
+}
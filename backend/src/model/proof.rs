@@ -1,16 +1,79 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Display;
 
 use generational_arena::{Arena, Index};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-use super::{StatementDTO, TreeStateDTO};
+use super::history::{Change, History};
+use super::protocol::IndexSchema;
+use super::verification::Verification;
+use super::{GroupDTO, StatementDTO, TreeDiffDTO, TreeStateDTO};
+use crate::ai::normalize;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub enum ProofError {
-    NoSuchNode(Index),
+    NoSuchNode(#[schemars(with = "IndexSchema")] Index),
     RemoveRoot,
-    AddExistingLink { child: Index, parent: Index },
-    RemoveNonExistentLink { child: Index, parent: Index },
+    NothingToUndo,
+    NothingToRedo,
+    AddExistingLink {
+        #[schemars(with = "IndexSchema")]
+        child: Index,
+        #[schemars(with = "IndexSchema")]
+        parent: Index,
+    },
+    RemoveNonExistentLink {
+        #[schemars(with = "IndexSchema")]
+        child: Index,
+        #[schemars(with = "IndexSchema")]
+        parent: Index,
+    },
+    CircularLink {
+        #[schemars(with = "IndexSchema")]
+        child: Index,
+        #[schemars(with = "IndexSchema")]
+        parent: Index,
+        #[schemars(with = "Vec<IndexSchema>")]
+        cycle: Vec<Index>,
+    },
+    NoSuchLink {
+        #[schemars(with = "IndexSchema")]
+        child: Index,
+        #[schemars(with = "IndexSchema")]
+        parent: Index,
+    },
+    AddExistingAttack {
+        #[schemars(with = "IndexSchema")]
+        attacker: Index,
+        target: AttackTarget,
+    },
+    NoSuchAttack {
+        #[schemars(with = "IndexSchema")]
+        attacker: Index,
+        target: AttackTarget,
+    },
+    CircularAttack {
+        #[schemars(with = "IndexSchema")]
+        attacker: Index,
+        target: AttackTarget,
+        #[schemars(with = "Vec<IndexSchema>")]
+        cycle: Vec<Index>,
+    },
+    NoSuchGroup {
+        #[schemars(with = "IndexSchema")]
+        id: Index,
+        group: usize,
+    },
+    /// the AI is not checking anything about the statement.
+    NotVerifying(#[schemars(with = "IndexSchema")] Index),
+    /// the undo history of a loaded game does not fit the game.
+    BrokenHistory,
+    /// the statement has more characters than the `statement_length` limit of the server.
+    StatementTooLong {
+        length: usize,
+        limit: usize,
+    },
 }
 
 impl Display for ProofError {
@@ -18,6 +81,8 @@ impl Display for ProofError {
         match self {
             ProofError::NoSuchNode(i) => write!(f, "No node with index {:?}.", i),
             ProofError::RemoveRoot => write!(f, "Tried to remove the root node."),
+            ProofError::NothingToUndo => write!(f, "There is nothing to undo."),
+            ProofError::NothingToRedo => write!(f, "There is nothing to redo."),
             ProofError::AddExistingLink { child, parent } => {
                 write!(f, "Tried to add an existing link from {:?} to {:?}.", child, parent)
             }
@@ -26,60 +91,193 @@ impl Display for ProofError {
                 "Tried to remove a non-existent link from {:?} to {:?}.",
                 child, parent
             ),
+            ProofError::CircularLink { child, parent, cycle } => write!(
+                f,
+                "Tried to add a link from {:?} to {:?}, which would create the cycle {}.",
+                child,
+                parent,
+                format_cycle(cycle)
+            ),
+            ProofError::NoSuchLink { child, parent } => write!(f, "There is no link from {:?} to {:?}.", child, parent),
+            ProofError::AddExistingAttack { attacker, target } => {
+                write!(f, "{:?} already attacks {:?}.", attacker, target)
+            }
+            ProofError::NoSuchAttack { attacker, target } => write!(f, "{:?} does not attack {:?}.", attacker, target),
+            ProofError::CircularAttack {
+                attacker,
+                target,
+                cycle,
+            } => write!(
+                f,
+                "Tried to let {:?} attack {:?}, which would create the cycle of attacks {}.",
+                attacker,
+                target,
+                format_cycle(cycle)
+            ),
+            ProofError::NoSuchGroup { id, group } => write!(f, "{:?} has no premise group {}.", id, group),
+            ProofError::NotVerifying(id) => write!(f, "The AI is not checking {:?}.", id),
+            ProofError::BrokenHistory => write!(f, "The undo history does not fit the game."),
+            ProofError::StatementTooLong { length, limit } => write!(
+                f,
+                "The statement has {} characters, at most {} are allowed.",
+                length, limit
+            ),
         }
     }
 }
 
+fn format_cycle(cycle: &[Index]) -> String {
+    cycle
+        .iter()
+        .map(|id| format!("{:?}", id))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// one way to prove a conclusion: premises that together imply it.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct PremiseGroup {
+    pub premises: Vec<Index>,
+    /// the AI accepted that the premises imply the conclusion.
+    pub accepted: bool,
+    /// the AI rejected that the premises imply the conclusion.
+    #[serde(default)]
+    pub rejected: bool,
+    /// hash of the premise texts the verdict was given for. The verdict is stale once a premise is edited,
+    /// `None` for verdicts from before hashes were kept.
+    #[serde(default)]
+    pub verified: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 struct StatementNode {
     statement: String,
-    children: Vec<Index>,
+    /// alternative ways to prove the statement, it is implied if any of them is accepted.
+    #[serde(default)]
+    groups: Vec<PremiseGroup>,
+    /// one entry per link, so a parent appears once for every group of it the node is in.
     parents: Vec<Index>,
     state: ProofState,
+    /// removed nodes stay in the arena, so undo can bring them back under the same id.
+    #[serde(default)]
+    removed: bool,
+    /// verdicts of the AI on this statement, oldest first.
+    #[serde(default)]
+    verifications: Vec<Verification>,
 }
 
 impl StatementNode {
     fn new(statement: String) -> Self {
         Self {
             statement,
-            children: Vec::new(),
+            groups: Vec::new(),
             parents: Vec::new(),
             state: ProofState::None,
+            removed: false,
+            verifications: Vec::new(),
         }
     }
 
-    fn is_proven(&self) -> bool { self.state.is_proven() }
+    /// premises of all groups, each once.
+    fn children(&self) -> Vec<Index> {
+        let mut children = Vec::new();
+        for &premise in self.groups.iter().flat_map(|group| &group.premises) {
+            if !children.contains(&premise) {
+                children.push(premise);
+            }
+        }
+        children
+    }
 
-    fn is_implied(&self) -> bool { self.state.is_implied() }
+    fn is_proven(&self) -> bool { self.state.is_proven() }
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
 pub enum ProofState {
     DirectlyProven,
     None,
     ImpliedUnproven, // gpt accepts that it is a consequence
     ImpliedProven,
+    DirectlyRefuted,     // gpt considers the statement false
+    ImplicationRejected, // gpt rejected every premise group it checked, and accepted none
 }
 
 impl ProofState {
-    fn is_proven(&self) -> bool {
-        match self {
-            ProofState::DirectlyProven => true,
-            ProofState::ImpliedProven => true,
-            _ => false,
+    fn is_proven(&self) -> bool { matches!(self, ProofState::DirectlyProven | ProofState::ImpliedProven) }
+}
+
+/// what an attack argues against: a statement, or the inference of a conclusion from one of its premises.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, JsonSchema)]
+pub enum AttackTarget {
+    Node(#[schemars(with = "IndexSchema")] Index),
+    Link {
+        #[schemars(with = "IndexSchema")]
+        premise: Index,
+        #[schemars(with = "IndexSchema")]
+        conclusion: Index,
+    },
+}
+
+impl AttackTarget {
+    /// the statement whose proof is affected by the attack.
+    pub fn affected(&self) -> Index {
+        match *self {
+            AttackTarget::Node(id) => id,
+            AttackTarget::Link { conclusion, .. } => conclusion,
         }
     }
-    fn is_implied(&self) -> bool {
-        match self {
-            ProofState::ImpliedProven => true,
-            ProofState::ImpliedUnproven => true,
-            _ => false,
-        }
+}
+
+/// `attacker` claims that `target` is wrong. The attack stands once the AI accepted it as `valid`,
+/// as long as the attacker is not defeated by a standing attack itself.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct Attack {
+    #[schemars(with = "IndexSchema")]
+    pub attacker: Index,
+    pub target: AttackTarget,
+    pub valid: bool,
+}
+
+impl Attack {
+    fn touches(&self, id: Index) -> bool {
+        self.attacker == id
+            || match self.target {
+                AttackTarget::Node(target) => target == id,
+                AttackTarget::Link { premise, conclusion } => premise == id || conclusion == id,
+            }
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
 pub struct TreeState {
     arena: Arena<StatementNode>,
     root: Index,
+    #[serde(default)]
+    attacks: Vec<Attack>,
+    #[serde(default)]
+    history: History,
+    /// increases with every change clients are told about.
+    #[serde(default)]
+    revision: u64,
+    /// what changed since the last revision.
+    #[serde(skip)]
+    touched: Touched,
+    /// what changed in each of the latest revisions, oldest first, so clients can catch up on what they missed.
+    #[serde(skip)]
+    revisions: VecDeque<Touched>,
+    /// nodes the AI is checking, with the number of checks still running.
+    #[serde(skip)]
+    verifying: HashMap<Index, usize>,
+}
+
+/// how many revisions clients can catch up on with a diff, older ones get the whole tree.
+const MAX_REVISIONS: usize = 100;
+
+/// nodes a change was applied to, and whether an attack changed.
+#[derive(Default, Clone)]
+struct Touched {
+    nodes: HashSet<Index>,
+    attacks: bool,
 }
 
 impl TreeState {
@@ -87,140 +285,859 @@ impl TreeState {
         let root = StatementNode::new(root_statement);
         let mut arena = Arena::new();
         let root_id = arena.insert(root);
-        Self { arena, root: root_id }
+        Self {
+            arena,
+            root: root_id,
+            attacks: Vec::new(),
+            history: History::default(),
+            revision: 0,
+            touched: Touched::default(),
+            revisions: VecDeque::new(),
+            verifying: HashMap::new(),
+        }
     }
 
-    pub fn as_dto(&self) -> TreeStateDTO {
-        let mut statements = Vec::<StatementDTO>::new();
+    /// check a tree that was read from a document, so a broken or tampered one can not crash the game later:
+    /// the tree and every state undo and redo can lead to are consistent, see `check_structure`,
+    /// and every change in the history fits the tree it is applied to.
+    pub fn validate(&self) -> Result<(), ProofError> {
+        self.check_structure()?;
+        // undo everything on a copy, then redo it all, like `undo` and `redo` would.
+        let mut tree = self.clone();
+        while let Some(operation) = tree.history.take_undo() {
+            for change in operation.iter().rev() {
+                tree.check_change(change, false)?;
+                tree.apply(change, false);
+            }
+            tree.check_structure()?;
+            tree.history.push_redo(operation);
+        }
+        while let Some(operation) = tree.history.take_redo() {
+            for change in operation.iter() {
+                tree.check_change(change, true)?;
+                tree.apply(change, true);
+            }
+            tree.check_structure()?;
+            tree.history.push_undo(operation);
+        }
+        Ok(())
+    }
+
+    /// the root and every id in groups, parents and attacks exist, links are known to both ends
+    /// and premises are not circular.
+    fn check_structure(&self) -> Result<(), ProofError> {
+        self.get_node(self.root)?;
+        let exists = |id: Index| {
+            if self.arena.contains(id) {
+                Ok(())
+            } else {
+                Err(ProofError::NoSuchNode(id))
+            }
+        };
         for (id, node) in self.arena.iter() {
-            statements.push(StatementDTO {
-                id,
-                statement: node.statement.clone(),
-                state: node.state.clone(),
-                parents: node.parents.clone(),
-                children: node.children.clone(),
-            });
+            for &premise in node.groups.iter().flat_map(|group| &group.premises) {
+                exists(premise)?;
+                if !node.removed && !self.get_node(premise)?.parents.contains(&id) {
+                    return Err(ProofError::NoSuchLink {
+                        child: premise,
+                        parent: id,
+                    });
+                }
+            }
+            for &parent in &node.parents {
+                exists(parent)?;
+                if !node.removed && !self.get_node(parent)?.children().contains(&id) {
+                    return Err(ProofError::NoSuchLink { child: id, parent });
+                }
+            }
+        }
+        for attack in &self.attacks {
+            self.get_node(attack.attacker)?;
+            self.check_target(attack.target)?;
+        }
+        self.check_acyclic()
+    }
+
+    /// no statement is among its own premises, directly or indirectly. Reports the first cycle like `link`.
+    fn check_acyclic(&self) -> Result<(), ProofError> {
+        let mut done = HashSet::new();
+        for (start, node) in self.arena.iter().filter(|(_, node)| !node.removed) {
+            if done.contains(&start) {
+                continue;
+            }
+            // the current path of conclusions to premises, each with the premises still to be visited.
+            let mut stack = vec![(start, node.children())];
+            let mut on_path = HashSet::from([start]);
+            while let Some((id, premises)) = stack.last_mut() {
+                let id = *id;
+                let Some(premise) = premises.pop() else {
+                    stack.pop();
+                    on_path.remove(&id);
+                    done.insert(id);
+                    continue;
+                };
+                if on_path.contains(&premise) {
+                    let mut cycle = vec![id];
+                    cycle.extend(
+                        stack
+                            .iter()
+                            .map(|(on_path, _)| *on_path)
+                            .skip_while(|&on_path| on_path != premise),
+                    );
+                    return Err(ProofError::CircularLink {
+                        parent: id,
+                        child: premise,
+                        cycle,
+                    });
+                }
+                if !done.contains(&premise) {
+                    on_path.insert(premise);
+                    stack.push((premise, self.arena[premise].children()));
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// texts of all statements, including removed ones and old texts that undo or redo could bring back.
+    pub fn statements(&self) -> impl Iterator<Item = &str> {
+        let history = self.history.changes().filter_map(|change| match change {
+            Change::Statement { old, new, .. } => Some([old.as_str(), new.as_str()]),
+            _ => None,
+        });
+        self.arena
+            .iter()
+            .map(|(_, node)| node.statement.as_str())
+            .chain(history.flatten())
+    }
+
+    /// start a new undo history, e.g. for a game built from an imported argument map.
+    pub fn forget_history(&mut self) { self.history = History::default(); }
+
+    pub fn as_dto(&self) -> TreeStateDTO {
+        let mut derived = Derived::default();
         TreeStateDTO {
-            statements,
+            statements: self
+                .arena
+                .iter()
+                .filter(|(_, node)| !node.removed)
+                .map(|(id, node)| self.node_dto(id, node, &mut derived))
+                .collect(),
+            attacks: self.attacks.clone(),
             root: self.root,
+            revision: self.revision,
+        }
+    }
+
+    pub fn revision(&self) -> u64 { self.revision }
+
+    /// start a new revision if anything changed since the last one.
+    pub fn commit_revision(&mut self) -> bool {
+        if self.touched.nodes.is_empty() && !self.touched.attacks {
+            return false;
+        }
+        self.revision += 1;
+        self.revisions.push_back(std::mem::take(&mut self.touched));
+        if self.revisions.len() > MAX_REVISIONS {
+            self.revisions.pop_front();
+        }
+        true
+    }
+
+    /// the statements that changed after revision `since`, including derived fields like `defeated`.
+    /// `None` if `since` is too old to be remembered, or newer than the tree.
+    pub fn diff_since(&self, since: u64) -> Option<TreeDiffDTO> {
+        let missed = usize::try_from(self.revision.checked_sub(since)?).ok()?;
+        if missed > self.revisions.len() {
+            return None;
+        }
+        let mut touched = Touched::default();
+        for revision in self.revisions.iter().skip(self.revisions.len() - missed) {
+            touched.nodes.extend(&revision.nodes);
+            touched.attacks |= revision.attacks;
+        }
+        let (mut changed, mut removed) = (Vec::new(), Vec::new());
+        let mut derived = Derived::default();
+        for id in self.dependents(touched.nodes) {
+            match self.arena.get(id).filter(|node| !node.removed) {
+                Some(node) => changed.push(self.node_dto(id, node, &mut derived)),
+                None => removed.push(id),
+            }
+        }
+        changed.sort_by_key(|node| node.id);
+        removed.sort();
+        Some(TreeDiffDTO {
+            base: since,
+            revision: self.revision,
+            statements: changed,
+            removed,
+            attacks: touched.attacks.then(|| self.attacks.clone()),
+        })
+    }
+
+    /// `nodes` and everything derived from them: the conclusions they are premises of,
+    /// and the statements they attack, transitively.
+    fn dependents(&self, nodes: HashSet<Index>) -> HashSet<Index> {
+        let mut todo: Vec<Index> = nodes.into_iter().collect();
+        let mut dependents = HashSet::new();
+        while let Some(id) = todo.pop() {
+            if !dependents.insert(id) {
+                continue;
+            }
+            if let Some(node) = self.arena.get(id) {
+                todo.extend(&node.parents);
+            }
+            todo.extend(
+                self.attacks
+                    .iter()
+                    .filter(|attack| attack.attacker == id)
+                    .map(|attack| attack.target.affected()),
+            );
+        }
+        dependents
+    }
+
+    /// the AI started checking the node, one of its premise groups or one of its attacks.
+    pub fn start_verifying(&mut self, id: Index) {
+        *self.verifying.entry(id).or_default() += 1;
+        self.touched.nodes.insert(id);
+    }
+
+    /// a check started with `start_verifying` finished or was cancelled.
+    pub fn stop_verifying(&mut self, id: Index) {
+        if let Some(count) = self.verifying.get_mut(&id) {
+            *count -= 1;
+            if *count == 0 {
+                self.verifying.remove(&id);
+            }
+            self.touched.nodes.insert(id);
+        }
+    }
+
+    /// keep a change for undo, and for the next revision.
+    fn record(&mut self, change: Change) {
+        self.touch(&change);
+        self.history.record(change);
+    }
+
+    fn touch(&mut self, change: &Change) {
+        let nodes = self.changed_nodes(change);
+        self.touched.nodes.extend(nodes);
+        if matches!(
+            change,
+            Change::AddAttack { .. } | Change::RemoveAttack { .. } | Change::AttackValid { .. }
+        ) {
+            self.touched.attacks = true;
+        }
+    }
+
+    /// the statements a change modifies or whose proof it affects.
+    fn changed_nodes(&self, change: &Change) -> Vec<Index> {
+        match *change {
+            Change::Add(id)
+            | Change::Remove(id)
+            | Change::Statement { id, .. }
+            | Change::AddGroup(id)
+            | Change::GroupAccepted { id, .. }
+            | Change::GroupRejected { id, .. }
+            | Change::GroupVerified { id, .. }
+            | Change::State { id, .. } => vec![id],
+            Change::Link { parent, child, .. } | Change::Unlink { parent, child, .. } => vec![parent, child],
+            Change::AddAttack { ref attack, .. } | Change::RemoveAttack { ref attack, .. } => {
+                vec![attack.attacker, attack.target.affected()]
+            }
+            Change::AttackValid { pos, .. } => self
+                .attacks
+                .get(pos)
+                .map_or_else(Vec::new, |attack| vec![attack.attacker, attack.target.affected()]),
+        }
+    }
+
+    fn node_dto(&self, id: Index, node: &StatementNode, derived: &mut Derived) -> StatementDTO {
+        StatementDTO {
+            id,
+            statement: node.statement.clone(),
+            state: node.state.clone(),
+            defeated: self.defeated(id, &mut derived.defeated),
+            unsupported: self.unsupported(id, derived),
+            verifying: self.verifying.contains_key(&id),
+            parents: node.parents.iter().fold(Vec::new(), |mut parents, &parent| {
+                if !parents.contains(&parent) {
+                    parents.push(parent);
+                }
+                parents
+            }),
+            children: node.children(),
+            groups: node
+                .groups
+                .iter()
+                .map(|group| GroupDTO {
+                    premises: group.premises.clone(),
+                    accepted: group.accepted,
+                    rejected: group.rejected,
+                    stale: self.is_stale(group),
+                })
+                .collect(),
         }
     }
 
     pub fn proof_complete(&self) -> bool { self.is_proven(self.root).expect("Root must exist.") }
 
     pub fn is_proven(&self, id: Index) -> Result<bool, ProofError> {
-        let n = self.get_node(id)?;
-        Ok(n.is_proven())
+        self.get_node(id)?;
+        Ok(self.holds(id))
     }
 
     pub fn get_statement(&self, id: Index) -> Result<&str, ProofError> { Ok(&self.get_node(id)?.statement) }
 
-    pub fn get_premises(&self, id: Index) -> Result<Vec<&str>, ProofError> {
+    pub fn get_premises(&self, id: Index, group: usize) -> Result<Vec<&str>, ProofError> {
         let node = self.get_node(id)?;
-        Ok(node
-            .children
+        let group = node.groups.get(group).ok_or(ProofError::NoSuchGroup { id, group })?;
+        Ok(group
+            .premises
             .iter()
             .map(|&child| self.get_statement(child).unwrap())
             .collect())
     }
 
+    pub fn get_verifications(&self, id: Index) -> Result<&[Verification], ProofError> {
+        Ok(&self.get_node(id)?.verifications)
+    }
+
+    /// keep a verdict of the AI on the node. Not part of the undo history.
+    pub fn add_verification(&mut self, id: Index, verification: Verification) -> Result<(), ProofError> {
+        self.get_node_mut(id)?.verifications.push(verification);
+        Ok(())
+    }
+
     pub fn add_node(&mut self, statement: String) -> Index {
         let node = StatementNode::new(statement);
-        self.arena.insert(node)
+        let id = self.arena.insert(node);
+        self.record(Change::Add(id));
+        self.history.commit();
+        id
     }
 
     /// remove any node. affects all ancestors.
     pub fn remove_node(&mut self, id: Index) -> Result<(), ProofError> {
+        let result = self.remove_node_changes(id);
+        self.finish(result)
+    }
+
+    fn remove_node_changes(&mut self, id: Index) -> Result<(), ProofError> {
         if id == self.root {
             return Err(ProofError::RemoveRoot);
         }
-        let node = self.arena.remove(id).ok_or(ProofError::NoSuchNode(id))?;
-        for parent_id in node.parents {
-            let _ = self.unlink(parent_id, id);
+        let node = self.get_node(id)?;
+        let mut links = Vec::new();
+        for &parent in node.parents.iter() {
+            for (group, premises) in self.get_node(parent)?.groups.iter().enumerate() {
+                if premises.premises.contains(&id) && !links.contains(&(parent, id, group)) {
+                    links.push((parent, id, group));
+                }
+            }
         }
-        for child_id in node.children {
-            let _ = self.unlink(id, child_id);
+        for (group, premises) in node.groups.iter().enumerate() {
+            links.extend(premises.premises.iter().map(|&child| (id, child, group)));
+        }
+        for (parent_id, child_id, group) in links {
+            self.remove_link(parent_id, child_id, group)?;
+        }
+        for pos in (0..self.attacks.len()).rev() {
+            if self.attacks[pos].touches(id) {
+                self.remove_attack(pos);
+            }
         }
+        self.get_node_mut(id)?.removed = true;
+        self.record(Change::Remove(id));
         Ok(())
     }
 
-    /// Change statement of a node. Does affect proof state.
+    /// Change statement of a node. Does affect proof state, and attacks by or on it have to be checked again.
     pub fn change_node_statement(&mut self, id: Index, new_statement: String) -> Result<(), ProofError> {
         let node = self.get_node_mut(id)?;
-        node.statement = new_statement;
+        let old = std::mem::replace(&mut node.statement, new_statement.clone());
+        self.record(Change::Statement {
+            id,
+            old,
+            new: new_statement,
+        });
         self.set_proof_state(id, ProofState::None);
+        for group in 0..self.get_node(id)?.groups.len() {
+            self.set_group_verdict(id, group, None);
+        }
+        // verdicts on implications from the old text are stale now.
+        let mut parents = self.get_node(id)?.parents.clone();
+        parents.sort();
+        parents.dedup();
+        for parent in parents {
+            self.on_child_change(parent);
+        }
+        for pos in 0..self.attacks.len() {
+            if self.attacks[pos].touches(id) {
+                self.set_attack_valid_at(pos, false);
+            }
+        }
+        self.history.commit();
         Ok(())
     }
 
-    /// Create implication-link. Affects parent state.
-    pub fn link(&mut self, parent_id: Index, child_id: Index) -> Result<(), ProofError> {
-        if parent_id == child_id {
-            let node = self.get_node_mut(parent_id)?;
-            node.parents.push(child_id);
-            node.children.push(parent_id);
-            return Ok(());
-        }
-        let (parent, child) = self.get2_node_mut(parent_id, child_id)?;
-        if parent.children.contains(&child_id) {
-            return Err(ProofError::AddExistingLink {
+    /// Create implication-link in a premise group of the parent, `group` may be the index of a new group.
+    /// Affects parent state. Links that would close a cycle are rejected.
+    pub fn link(&mut self, parent_id: Index, child_id: Index, group: usize) -> Result<(), ProofError> {
+        self.get_node(parent_id)?;
+        self.get_node(child_id)?;
+        if let Some(path) = self.find_premise_path(child_id, parent_id) {
+            let mut cycle = vec![parent_id];
+            cycle.extend(path);
+            return Err(ProofError::CircularLink {
                 parent: parent_id,
                 child: child_id,
+                cycle,
             });
         }
-        parent.children.push(child_id);
+        let groups = &self.get_node(parent_id)?.groups;
+        match groups.get(group) {
+            Some(premises) if premises.premises.contains(&child_id) => {
+                return Err(ProofError::AddExistingLink {
+                    parent: parent_id,
+                    child: child_id,
+                });
+            }
+            Some(_) => {}
+            None if group == groups.len() => {
+                self.get_node_mut(parent_id)?.groups.push(PremiseGroup::default());
+                self.record(Change::AddGroup(parent_id));
+            }
+            None => return Err(ProofError::NoSuchGroup { id: parent_id, group }),
+        }
+        let stale = self.is_stale(&self.get_node(parent_id)?.groups[group]);
+        let (parent, child) = self.get2_node_mut(parent_id, child_id)?;
+        let premises = &mut parent.groups[group];
+        let (child_pos, parent_pos) = (premises.premises.len(), child.parents.len());
+        premises.premises.push(child_id);
         child.parents.push(parent_id);
-        if parent.is_implied() {
+        let (accepted, rejected) = (premises.accepted, premises.rejected);
+        self.record(Change::Link {
+            parent: parent_id,
+            child: child_id,
+            group,
+            child_pos,
+            parent_pos,
+        });
+        if rejected {
+            // the new premise might be what was missing.
+            self.set_group_verdict(parent_id, group, None);
+        } else if accepted && !stale {
+            // more premises still imply the conclusion, so the verdict holds for the new premise texts.
+            self.set_group_verdict(parent_id, group, Some(true));
+        }
+        if accepted || rejected {
             // implication stays in place, but truth value might change.
             self.on_child_change(parent_id);
         }
+        self.history.commit();
         Ok(())
     }
 
-    /// Remove implication-link. Affects parent state.
-    pub fn unlink(&mut self, parent_id: Index, child_id: Index) -> Result<(), ProofError> {
-        if parent_id == child_id {
-            let node = self.get_node_mut(parent_id)?;
-            node.parents.retain(|&x| x != child_id);
-            node.children.retain(|&x| x != parent_id);
-            return Ok(());
-        }
-        let (parent, child) = self.get2_node_mut(parent_id, child_id)?;
-        if !parent.children.contains(&child_id) {
+    /// Remove implication-link from one premise group of the parent, or from all groups if `group` is `None`.
+    /// Affects parent state.
+    pub fn unlink(&mut self, parent_id: Index, child_id: Index, group: Option<usize>) -> Result<(), ProofError> {
+        let result = self.unlink_changes(parent_id, child_id, group);
+        self.finish(result)
+    }
+
+    fn unlink_changes(&mut self, parent_id: Index, child_id: Index, group: Option<usize>) -> Result<(), ProofError> {
+        let groups: Vec<usize> = match group {
+            Some(group) => vec![group],
+            None => self
+                .get_node(parent_id)?
+                .groups
+                .iter()
+                .enumerate()
+                .filter(|(_, premises)| premises.premises.contains(&child_id))
+                .map(|(group, _)| group)
+                .collect(),
+        };
+        if groups.is_empty() {
+            self.get_node(child_id)?;
             return Err(ProofError::RemoveNonExistentLink {
                 parent: parent_id,
                 child: child_id,
             });
         }
-        parent.children.retain(|&x| x != child_id);
-        child.parents.retain(|&x| x != parent_id);
-        if parent.is_implied() {
-            self.set_proof_state(parent_id, ProofState::None);
+        for group in groups {
+            self.remove_link(parent_id, child_id, group)?;
         }
         Ok(())
     }
 
-    /// AI accepts a statement by itself
-    pub fn set_directly_proven(&mut self, id: Index) { self.set_proof_state(id, ProofState::DirectlyProven) }
+    /// AI accepts a statement by itself. Like all verdicts of the AI, an operation of its own that can be undone.
+    pub fn set_directly_proven(&mut self, id: Index) {
+        self.set_proof_state(id, ProofState::DirectlyProven);
+        self.history.commit();
+    }
 
-    /// AI accepts a statement as a consequence its children
-    pub fn set_implied(&mut self, id: Index) {
-        let new = {
-            if self.check_if_all_children_true(id) {
-                ProofState::ImpliedProven
-            } else {
-                ProofState::ImpliedUnproven
+    /// AI accepts a statement as a consequence of one of its premise groups
+    pub fn set_implied(&mut self, id: Index, group: usize) {
+        self.set_group_verdict(id, group, Some(true));
+        self.set_proof_state(id, self.implied_state(id));
+        self.history.commit();
+    }
+
+    /// AI considers a statement false by itself
+    pub fn set_directly_refuted(&mut self, id: Index) {
+        self.set_proof_state(id, ProofState::DirectlyRefuted);
+        self.history.commit();
+    }
+
+    /// AI rejects a statement as a consequence of one of its premise groups. Only changes the state of
+    /// statements that are not decided otherwise.
+    pub fn set_implication_rejected(&mut self, id: Index, group: usize) {
+        self.set_group_verdict(id, group, Some(false));
+        self.on_child_change(id);
+        self.history.commit();
+    }
+
+    pub fn get_attack(&self, attacker: Index, target: AttackTarget) -> Result<&Attack, ProofError> {
+        self.find_attack(attacker, target)
+            .map(|pos| &self.attacks[pos])
+            .ok_or(ProofError::NoSuchAttack { attacker, target })
+    }
+
+    /// `attacker` argues against `target`. Does not affect proofs until the AI accepts the attack.
+    /// Attacks that would close a cycle of attacks are rejected.
+    pub fn attack(&mut self, attacker: Index, target: AttackTarget) -> Result<(), ProofError> {
+        self.get_node(attacker)?;
+        self.check_target(target)?;
+        let affected = target.affected();
+        if let Some(mut cycle) = self.find_path(attacker, affected, &|id| self.attackers(id)) {
+            cycle.reverse();
+            cycle.push(affected);
+            return Err(ProofError::CircularAttack {
+                attacker,
+                target,
+                cycle,
+            });
+        }
+        if self.find_attack(attacker, target).is_some() {
+            return Err(ProofError::AddExistingAttack { attacker, target });
+        }
+        let attack = Attack {
+            attacker,
+            target,
+            valid: false,
+        };
+        self.record(Change::AddAttack {
+            pos: self.attacks.len(),
+            attack: attack.clone(),
+        });
+        self.attacks.push(attack);
+        self.history.commit();
+        Ok(())
+    }
+
+    /// withdraw an attack. Affects the target state.
+    pub fn unattack(&mut self, attacker: Index, target: AttackTarget) -> Result<(), ProofError> {
+        let pos = self
+            .find_attack(attacker, target)
+            .ok_or(ProofError::NoSuchAttack { attacker, target })?;
+        self.remove_attack(pos);
+        self.history.commit();
+        Ok(())
+    }
+
+    /// AI accepts or rejects an attack. Affects the target state.
+    pub fn set_attack_valid(&mut self, attacker: Index, target: AttackTarget, valid: bool) -> Result<(), ProofError> {
+        let pos = self
+            .find_attack(attacker, target)
+            .ok_or(ProofError::NoSuchAttack { attacker, target })?;
+        self.set_attack_valid_at(pos, valid);
+        self.history.commit();
+        Ok(())
+    }
+
+    /// keep the changes of an operation for undo if it succeeded, otherwise revert what it changed before failing.
+    fn finish(&mut self, result: Result<(), ProofError>) -> Result<(), ProofError> {
+        match result {
+            Ok(()) => self.history.commit(),
+            Err(_) => {
+                for change in self.history.discard().iter().rev() {
+                    self.apply(change, false);
+                }
+            }
+        }
+        result
+    }
+
+    /// revert the last operation, including all proof state changes it caused.
+    pub fn undo(&mut self) -> Result<(), ProofError> {
+        let operation = self.history.take_undo().ok_or(ProofError::NothingToUndo)?;
+        for change in operation.iter().rev() {
+            self.apply(change, false);
+        }
+        self.history.push_redo(operation);
+        Ok(())
+    }
+
+    /// repeat the last undone operation.
+    pub fn redo(&mut self) -> Result<(), ProofError> {
+        let operation = self.history.take_redo().ok_or(ProofError::NothingToRedo)?;
+        for change in operation.iter() {
+            self.apply(change, true);
+        }
+        self.history.push_undo(operation);
+        Ok(())
+    }
+
+    /// whether `apply` can replay (`forward`) or revert `change`: the statements, groups and positions it refers to
+    /// exist, and what it removes is where it was recorded.
+    fn check_change(&self, change: &Change, forward: bool) -> Result<(), ProofError> {
+        let node = |id: Index| self.arena.get(id).ok_or(ProofError::NoSuchNode(id));
+        let premises = |id: Index, group: usize| {
+            node(id)?
+                .groups
+                .get(group)
+                .map(|group| &group.premises)
+                .ok_or(ProofError::NoSuchGroup { id, group })
+        };
+        let fits = match *change {
+            Change::Add(id) | Change::Remove(id) | Change::Statement { id, .. } | Change::State { id, .. } => {
+                node(id).map(|_| true)?
+            }
+            Change::AddGroup(id) => {
+                let groups = &node(id)?.groups;
+                forward || groups.last().is_some_and(|group| group.premises.is_empty())
+            }
+            Change::Link {
+                parent,
+                child,
+                group,
+                child_pos,
+                parent_pos,
+            }
+            | Change::Unlink {
+                parent,
+                child,
+                group,
+                child_pos,
+                parent_pos,
+            } => {
+                let (premises, parents) = (premises(parent, group)?, &node(child)?.parents);
+                if forward == matches!(change, Change::Link { .. }) {
+                    child_pos <= premises.len() && parent_pos <= parents.len()
+                } else {
+                    premises.get(child_pos) == Some(&child) && parents.get(parent_pos) == Some(&parent)
+                }
             }
+            Change::GroupAccepted { id, group, .. }
+            | Change::GroupRejected { id, group, .. }
+            | Change::GroupVerified { id, group, .. } => premises(id, group).map(|_| true)?,
+            Change::AddAttack { pos, ref attack } | Change::RemoveAttack { pos, ref attack } => {
+                if forward == matches!(change, Change::AddAttack { .. }) {
+                    pos <= self.attacks.len()
+                } else {
+                    self.attacks.get(pos) == Some(attack)
+                }
+            }
+            Change::AttackValid { pos, .. } => pos < self.attacks.len(),
         };
-        self.set_proof_state(id, new);
+        if fits {
+            Ok(())
+        } else {
+            Err(ProofError::BrokenHistory)
+        }
+    }
+
+    /// replay (`forward`) or revert a recorded change. Proof states are restored as recorded, not recomputed.
+    fn apply(&mut self, change: &Change, forward: bool) {
+        self.touch(change);
+        match *change {
+            Change::Add(id) => self.arena[id].removed = !forward,
+            Change::Remove(id) => self.arena[id].removed = forward,
+            Change::Statement { id, ref old, ref new } => {
+                self.arena[id].statement = if forward { new } else { old }.clone();
+            }
+            Change::AddGroup(id) => {
+                if forward {
+                    self.arena[id].groups.push(PremiseGroup::default());
+                } else {
+                    self.arena[id].groups.pop();
+                }
+            }
+            Change::Link {
+                parent,
+                child,
+                group,
+                child_pos,
+                parent_pos,
+            } => {
+                if forward {
+                    self.insert_link_at(parent, child, group, child_pos, parent_pos);
+                } else {
+                    self.remove_link_at(parent, child, group, child_pos, parent_pos);
+                }
+            }
+            Change::Unlink {
+                parent,
+                child,
+                group,
+                child_pos,
+                parent_pos,
+            } => {
+                if forward {
+                    self.remove_link_at(parent, child, group, child_pos, parent_pos);
+                } else {
+                    self.insert_link_at(parent, child, group, child_pos, parent_pos);
+                }
+            }
+            Change::GroupAccepted { id, group, old, new } => {
+                self.arena[id].groups[group].accepted = if forward { new } else { old };
+            }
+            Change::GroupRejected { id, group, old, new } => {
+                self.arena[id].groups[group].rejected = if forward { new } else { old };
+            }
+            Change::GroupVerified { id, group, old, new } => {
+                self.arena[id].groups[group].verified = if forward { new } else { old };
+            }
+            Change::State { id, ref old, ref new } => {
+                self.arena[id].state = if forward { new } else { old }.clone();
+            }
+            Change::AddAttack { pos, ref attack } => {
+                if forward {
+                    self.attacks.insert(pos, attack.clone());
+                } else {
+                    self.attacks.remove(pos);
+                }
+            }
+            Change::RemoveAttack { pos, ref attack } => {
+                if forward {
+                    self.attacks.remove(pos);
+                } else {
+                    self.attacks.insert(pos, attack.clone());
+                }
+            }
+            Change::AttackValid { pos, old, new } => self.attacks[pos].valid = if forward { new } else { old },
+        }
+    }
+
+    fn insert_link_at(&mut self, parent: Index, child: Index, group: usize, child_pos: usize, parent_pos: usize) {
+        self.arena[parent].groups[group].premises.insert(child_pos, child);
+        self.arena[child].parents.insert(parent_pos, parent);
+    }
+
+    fn remove_link_at(&mut self, parent: Index, child: Index, group: usize, child_pos: usize, parent_pos: usize) {
+        self.arena[parent].groups[group].premises.remove(child_pos);
+        self.arena[child].parents.remove(parent_pos);
+    }
+
+    /// remove a link from a premise group without finishing the operation.
+    fn remove_link(&mut self, parent_id: Index, child_id: Index, group: usize) -> Result<(), ProofError> {
+        if parent_id == child_id {
+            // self-links are never created, see `link`.
+            self.get_node(parent_id)?;
+            return Err(ProofError::RemoveNonExistentLink {
+                parent: parent_id,
+                child: child_id,
+            });
+        }
+        let groups = &self.get_node(parent_id)?.groups;
+        let premises = groups
+            .get(group)
+            .ok_or(ProofError::NoSuchGroup { id: parent_id, group })?;
+        let linked = premises.premises.contains(&child_id);
+        let linked_elsewhere = groups
+            .iter()
+            .enumerate()
+            .any(|(other, premises)| other != group && premises.premises.contains(&child_id));
+        self.get_node(child_id)?;
+        if !linked {
+            return Err(ProofError::RemoveNonExistentLink {
+                parent: parent_id,
+                child: child_id,
+            });
+        }
+        if !linked_elsewhere {
+            let link = AttackTarget::Link {
+                premise: child_id,
+                conclusion: parent_id,
+            };
+            for pos in (0..self.attacks.len()).rev() {
+                if self.attacks[pos].target == link {
+                    self.remove_attack(pos);
+                }
+            }
+        }
+        let (parent, child) = self.get2_node_mut(parent_id, child_id)?;
+        let premises = &mut parent.groups[group];
+        let child_pos = premises.premises.iter().position(|&x| x == child_id).unwrap();
+        let parent_pos = child.parents.iter().position(|&x| x == parent_id).unwrap();
+        premises.premises.remove(child_pos);
+        child.parents.remove(parent_pos);
+        let (accepted, rejected) = (premises.accepted, premises.rejected);
+        self.record(Change::Unlink {
+            parent: parent_id,
+            child: child_id,
+            group,
+            child_pos,
+            parent_pos,
+        });
+        if accepted || rejected {
+            // the AI judged other premises, it has to check the group again.
+            self.set_group_verdict(parent_id, group, None);
+            self.on_child_change(parent_id);
+        }
+        Ok(())
+    }
+
+    /// record whether the AI accepted (`Some(true)`) or rejected (`Some(false)`) a premise group,
+    /// `None` if the group has to be checked again.
+    fn set_group_verdict(&mut self, id: Index, group: usize, verdict: Option<bool>) {
+        let verified = verdict.map(|_| self.premises_hash(&self.get_node(id).unwrap().groups[group]));
+        let premises = &mut self.get_node_mut(id).unwrap().groups[group];
+        let (accepted, rejected) = (verdict == Some(true), verdict == Some(false));
+        let (old_accepted, old_rejected) = (premises.accepted, premises.rejected);
+        let old_verified = std::mem::replace(&mut premises.verified, verified);
+        premises.accepted = accepted;
+        premises.rejected = rejected;
+        if old_verified != verified {
+            self.record(Change::GroupVerified {
+                id,
+                group,
+                old: old_verified,
+                new: verified,
+            });
+        }
+        if old_accepted != accepted {
+            self.record(Change::GroupAccepted {
+                id,
+                group,
+                old: old_accepted,
+                new: accepted,
+            });
+        }
+        if old_rejected != rejected {
+            self.record(Change::GroupRejected {
+                id,
+                group,
+                old: old_rejected,
+                new: rejected,
+            });
+        }
     }
 
     fn get_node(&self, id: Index) -> Result<&StatementNode, ProofError> {
-        self.arena.get(id).ok_or(ProofError::NoSuchNode(id))
+        self.arena
+            .get(id)
+            .filter(|node| !node.removed)
+            .ok_or(ProofError::NoSuchNode(id))
     }
 
     fn get_node_mut(&mut self, id: Index) -> Result<&mut StatementNode, ProofError> {
-        self.arena.get_mut(id).ok_or(ProofError::NoSuchNode(id))
+        self.arena
+            .get_mut(id)
+            .filter(|node| !node.removed)
+            .ok_or(ProofError::NoSuchNode(id))
     }
 
     fn get2_node_mut(
@@ -229,50 +1146,766 @@ impl TreeState {
         id2: Index,
     ) -> Result<(&mut StatementNode, &mut StatementNode), ProofError> {
         match self.arena.get2_mut(id1, id2) {
-            (Some(a), Some(b)) => Ok((a, b)),
+            (Some(a), Some(b)) if !a.removed && !b.removed => Ok((a, b)),
+            (Some(a), Some(_)) if !a.removed => Err(ProofError::NoSuchNode(id2)),
+            (Some(_), Some(_)) => Err(ProofError::NoSuchNode(id1)),
             (None, _) => Err(ProofError::NoSuchNode(id1)),
             _ => Err(ProofError::NoSuchNode(id2)),
         }
     }
 
+    /// path from `from` to `to` following premise links, both ends included.
+    fn find_premise_path(&self, from: Index, to: Index) -> Option<Vec<Index>> {
+        self.find_path(from, to, &|id| self.get_node(id).unwrap().children())
+    }
+
+    /// path from `from` to `to` following the edges given by `next`, both ends included.
+    fn find_path(&self, from: Index, to: Index, next: &dyn Fn(Index) -> Vec<Index>) -> Option<Vec<Index>> {
+        // depth-first search without recursion, so long chains of statements can not overflow the stack.
+        // `stack` is the current path, each node with its successors that are still to be tried.
+        if from == to {
+            return Some(vec![from]);
+        }
+        let mut visited = HashSet::from([from]);
+        let mut stack = vec![(from, Self::successors(from, next))];
+        while let Some((_, successors)) = stack.last_mut() {
+            let Some(node) = successors.pop() else {
+                stack.pop();
+                continue;
+            };
+            if node == to {
+                let mut path: Vec<Index> = stack.into_iter().map(|(id, _)| id).collect();
+                path.push(node);
+                return Some(path);
+            }
+            if visited.insert(node) {
+                stack.push((node, Self::successors(node, next)));
+            }
+        }
+        None
+    }
+
+    /// successors of `id` in the order they are popped off the stack of `find_path`.
+    fn successors(id: Index, next: &dyn Fn(Index) -> Vec<Index>) -> Vec<Index> {
+        let mut successors = next(id);
+        successors.reverse();
+        successors
+    }
+
+    fn check_target(&self, target: AttackTarget) -> Result<(), ProofError> {
+        match target {
+            AttackTarget::Node(id) => self.get_node(id).map(|_| ()),
+            AttackTarget::Link { premise, conclusion } => {
+                let linked = self.get_node(conclusion)?.children().contains(&premise);
+                self.get_node(premise)?;
+                if !linked {
+                    return Err(ProofError::NoSuchLink {
+                        child: premise,
+                        parent: conclusion,
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn find_attack(&self, attacker: Index, target: AttackTarget) -> Option<usize> {
+        self.attacks
+            .iter()
+            .position(|attack| attack.attacker == attacker && attack.target == target)
+    }
+
+    /// all nodes attacking `id` or one of its inferences.
+    fn attackers(&self, id: Index) -> Vec<Index> {
+        self.attacks
+            .iter()
+            .filter(|attack| attack.target.affected() == id)
+            .map(|attack| attack.attacker)
+            .collect()
+    }
+
+    /// remove an attack without finishing the operation.
+    fn remove_attack(&mut self, pos: usize) {
+        let attack = self.attacks.remove(pos);
+        let target = attack.target;
+        self.record(Change::RemoveAttack { pos, attack });
+        self.on_attack_change(target);
+    }
+
+    fn set_attack_valid_at(&mut self, pos: usize, valid: bool) {
+        let attack = &mut self.attacks[pos];
+        if attack.valid == valid {
+            return;
+        }
+        attack.valid = valid;
+        let target = attack.target;
+        self.record(Change::AttackValid {
+            pos,
+            old: !valid,
+            new: valid,
+        });
+        self.on_attack_change(target);
+    }
+
+    /// whether a node counts as proven: proven by its state, and not defeated.
+    fn holds(&self, id: Index) -> bool { self.get_node(id).unwrap().is_proven() && !self.is_defeated(id) }
+
+    fn is_defeated(&self, id: Index) -> bool { self.defeated(id, &mut HashMap::new()) }
+
+    /// under a standing attack: one the AI accepted, by an attacker that is not defeated itself.
+    /// Each attacker is decided once and kept in `defeated`. Terminates, as attacks never form a cycle.
+    fn defeated(&self, id: Index, defeated: &mut HashMap<Index, bool>) -> bool {
+        memoize(
+            id,
+            defeated,
+            |id| self.standing_attackers(id),
+            |id, defeated| self.standing_attackers(id).iter().any(|attacker| !defeated[attacker]),
+        )
+    }
+
+    /// attackers of the statement `id` the AI agreed with, whether they are defeated or not.
+    fn standing_attackers(&self, id: Index) -> Vec<Index> {
+        self.attacks
+            .iter()
+            .filter(|attack| attack.valid && attack.target == AttackTarget::Node(id))
+            .map(|attack| attack.attacker)
+            .collect()
+    }
+
+    /// has premise groups, but none of them can prove it: each is rejected or contains a premise that is refuted
+    /// by the AI, defeated or unsupported itself. Statements proven directly do not depend on their premises.
+    fn unsupported(&self, id: Index, derived: &mut Derived) -> bool {
+        let Derived { defeated, unsupported } = derived;
+        memoize(
+            id,
+            unsupported,
+            |id| self.arena[id].children(),
+            |id, unsupported| {
+                let node = &self.arena[id];
+                let mut refuted = |premise: Index| {
+                    self.arena[premise].state == ProofState::DirectlyRefuted
+                        || self.defeated(premise, defeated)
+                        || unsupported[&premise]
+                };
+                node.state != ProofState::DirectlyProven
+                    && !node.groups.is_empty()
+                    && node
+                        .groups
+                        .iter()
+                        .all(|group| self.is_rejected(group) || group.premises.iter().any(|&premise| refuted(premise)))
+            },
+        )
+    }
+
+    /// the premises were edited since the AI judged the group.
+    fn is_stale(&self, group: &PremiseGroup) -> bool {
+        (group.accepted || group.rejected) && group.verified.is_some_and(|hash| hash != self.premises_hash(group))
+    }
+
+    fn is_accepted(&self, group: &PremiseGroup) -> bool { group.accepted && !self.is_stale(group) }
+
+    fn is_rejected(&self, group: &PremiseGroup) -> bool { group.rejected && !self.is_stale(group) }
+
+    /// FNV-1a of the normalized premise texts, in the order the verdict cache uses.
+    /// Unlike `DefaultHasher` it is stable across builds, as hashes are stored with the game.
+    fn premises_hash(&self, group: &PremiseGroup) -> u64 {
+        let mut premises: Vec<String> = group
+            .premises
+            .iter()
+            .map(|&premise| normalize(&self.arena[premise].statement))
+            .collect();
+        premises.sort();
+        premises.dedup();
+        premises.join("\n").bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn is_attacked(&self, target: AttackTarget) -> bool {
+        self.attacks
+            .iter()
+            .any(|attack| attack.valid && attack.target == target && !self.is_defeated(attack.attacker))
+    }
+
     /// trickle up the proof state.
     fn set_proof_state(&mut self, id: Index, new_state: ProofState) {
+        let old_truth = self.holds(id);
         let node = self.get_node_mut(id).unwrap();
-        let old_truth = node.is_proven();
-        node.state = new_state;
-        let new_truth = node.is_proven();
-        if old_truth != new_truth {
-            let parents = &node.parents.clone();
-            for &parent in parents {
-                self.on_child_change(parent);
-            }
+        if node.state != new_state {
+            let old = std::mem::replace(&mut node.state, new_state.clone());
+            self.record(Change::State {
+                id,
+                old,
+                new: new_state,
+            });
+        }
+        if old_truth != self.holds(id) {
+            self.on_truth_change(id);
+        }
+    }
+
+    /// `id` might have become (un)proven or (un)defeated: recheck everything depending on it.
+    fn on_truth_change(&mut self, id: Index) {
+        let parents = self.get_node(id).unwrap().parents.clone();
+        for parent in parents {
+            self.on_child_change(parent);
+        }
+        let targets: Vec<AttackTarget> = self
+            .attacks
+            .iter()
+            .filter(|attack| attack.attacker == id)
+            .map(|attack| attack.target)
+            .collect();
+        for target in targets {
+            self.on_attack_change(target);
+        }
+    }
+
+    /// an attack on `target` might have started or stopped standing.
+    fn on_attack_change(&mut self, target: AttackTarget) {
+        match target {
+            AttackTarget::Node(id) => self.on_truth_change(id),
+            AttackTarget::Link { conclusion, .. } => self.on_child_change(conclusion),
         }
     }
 
     /// recheck this node
+    /// statements the AI judged by themselves do not depend on their premises.
     fn on_child_change(&mut self, id: Index) {
         let node = self.get_node(id).unwrap();
-        if node.is_implied() {
-            let new = {
-                if self.check_if_all_children_true(id) {
-                    ProofState::ImpliedProven
-                } else {
-                    ProofState::ImpliedUnproven
+        if !matches!(node.state, ProofState::DirectlyProven | ProofState::DirectlyRefuted) {
+            self.set_proof_state(id, self.implied_state(id));
+        }
+    }
+
+    /// proven if any accepted premise group is complete. `ImplicationRejected` if no group is accepted,
+    /// but some are rejected, `None` if no group is judged. Stale verdicts do not count.
+    fn implied_state(&self, id: Index) -> ProofState {
+        let groups = &self.get_node(id).unwrap().groups;
+        let mut accepted = groups.iter().filter(|group| self.is_accepted(group)).peekable();
+        if accepted.peek().is_none() {
+            if groups.iter().any(|group| self.is_rejected(group)) {
+                ProofState::ImplicationRejected
+            } else {
+                ProofState::None
+            }
+        } else if accepted.any(|group| self.is_group_complete(id, group)) {
+            ProofState::ImpliedProven
+        } else {
+            ProofState::ImpliedUnproven
+        }
+    }
+
+    /// all premises hold, and no inference from them to `id` is under a standing attack.
+    fn is_group_complete(&self, id: Index, group: &PremiseGroup) -> bool {
+        group.premises.iter().all(|&premise| {
+            self.holds(premise)
+                && !self.is_attacked(AttackTarget::Link {
+                    premise,
+                    conclusion: id,
+                })
+        })
+    }
+}
+
+/// `defeated` and `unsupported` of the statements of a tree as it is, so statements shared by many conclusions
+/// are only decided once when all of them are sent.
+#[derive(Default)]
+struct Derived {
+    defeated: HashMap<Index, bool>,
+    unsupported: HashMap<Index, bool>,
+}
+
+/// `value` of `start`, computed after the values of everything it depends on and kept in `memo`.
+/// Values already in `memo` are not computed again. `dependencies` must not form a cycle.
+fn memoize(
+    start: Index,
+    memo: &mut HashMap<Index, bool>,
+    dependencies: impl Fn(Index) -> Vec<Index>,
+    mut value: impl FnMut(Index, &HashMap<Index, bool>) -> bool,
+) -> bool {
+    // without recursion, like `find_path`. A node is computed once it comes up again with `ready` set,
+    // after all dependencies pushed above it.
+    let mut stack = vec![(start, false)];
+    while let Some((id, ready)) = stack.pop() {
+        if memo.contains_key(&id) {
+            continue;
+        }
+        if ready {
+            let result = value(id, memo);
+            memo.insert(id, result);
+        } else {
+            stack.push((id, true));
+            for dependency in dependencies(id) {
+                if !memo.contains_key(&dependency) {
+                    stack.push((dependency, false));
                 }
-            };
-            self.set_proof_state(id, new);
+            }
+        }
+    }
+    memo[&start]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::verification::VerificationKind;
+    use super::*;
+
+    /// root implied by a directly proven premise.
+    fn proven_tree() -> (TreeState, Index) {
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let premise = tree.add_node("Every man is mortal.".into());
+        tree.link(tree.root, premise, 0).unwrap();
+        tree.set_directly_proven(premise);
+        tree.set_implied(tree.root, 0);
+        assert!(tree.proof_complete());
+        (tree, premise)
+    }
+
+    #[test]
+    fn standing_attack_defeats_proof() {
+        let (mut tree, premise) = proven_tree();
+        let attacker = tree.add_node("Some men lived forever.".into());
+        tree.attack(attacker, AttackTarget::Node(premise)).unwrap();
+        assert!(tree.proof_complete(), "attacks only count once the AI accepted them");
+
+        tree.set_attack_valid(attacker, AttackTarget::Node(premise), true)
+            .unwrap();
+        assert!(tree.is_defeated(premise));
+        assert_eq!(tree.arena[tree.root].state, ProofState::ImpliedUnproven);
+        assert!(!tree.proof_complete());
+
+        let counter = tree.add_node("Nobody lived forever.".into());
+        tree.attack(counter, AttackTarget::Node(attacker)).unwrap();
+        tree.set_attack_valid(counter, AttackTarget::Node(attacker), true)
+            .unwrap();
+        assert!(tree.proof_complete(), "a defeated attacker does not defeat anything");
+
+        tree.undo().unwrap();
+        assert!(!tree.proof_complete());
+        tree.unattack(attacker, AttackTarget::Node(premise)).unwrap();
+        assert!(tree.proof_complete());
+    }
+
+    #[test]
+    fn attack_on_inference() {
+        let (mut tree, premise) = proven_tree();
+        let attacker = tree.add_node("Socrates is a god.".into());
+        let link = AttackTarget::Link {
+            premise,
+            conclusion: tree.root,
+        };
+        tree.attack(attacker, link).unwrap();
+        tree.set_attack_valid(attacker, link, true).unwrap();
+        assert!(!tree.proof_complete());
+        assert!(tree.is_proven(premise).unwrap(), "the premise itself is not attacked");
+
+        tree.unlink(tree.root, premise, None).unwrap();
+        assert!(tree.attacks.is_empty(), "attacks on a removed link are removed too");
+        tree.undo().unwrap();
+        assert_eq!(tree.attacks.len(), 1);
+        assert!(matches!(
+            tree.attack(
+                attacker,
+                AttackTarget::Link {
+                    premise: attacker,
+                    conclusion: tree.root
+                }
+            ),
+            Err(ProofError::NoSuchLink { .. })
+        ));
+    }
+
+    #[test]
+    fn rejections_propagate_as_unsupported() {
+        let (mut tree, premise) = proven_tree();
+        tree.set_directly_refuted(premise);
+        assert_eq!(tree.arena[premise].state, ProofState::DirectlyRefuted);
+        assert_eq!(tree.arena[tree.root].state, ProofState::ImpliedUnproven);
+        assert!(
+            tree.unsupported(tree.root, &mut Derived::default()),
+            "the only group contains a refuted premise"
+        );
+
+        let other = tree.add_node("Socrates is a man.".into());
+        tree.link(tree.root, other, 1).unwrap();
+        assert!(
+            !tree.unsupported(tree.root, &mut Derived::default()),
+            "group 1 is not judged yet"
+        );
+        tree.set_implication_rejected(tree.root, 1);
+        assert!(tree.arena[tree.root].groups[1].rejected);
+        assert_eq!(
+            tree.arena[tree.root].state,
+            ProofState::ImpliedUnproven,
+            "group 0 is still accepted"
+        );
+        assert!(tree.unsupported(tree.root, &mut Derived::default()));
+
+        tree.unlink(tree.root, premise, Some(0)).unwrap();
+        assert_eq!(tree.arena[tree.root].state, ProofState::ImplicationRejected);
+        tree.link(tree.root, premise, 1).unwrap();
+        assert!(
+            !tree.arena[tree.root].groups[1].rejected,
+            "the new premise has to be checked"
+        );
+        assert_eq!(tree.arena[tree.root].state, ProofState::None);
+
+        tree.undo().unwrap();
+        assert_eq!(tree.arena[tree.root].state, ProofState::ImplicationRejected);
+        tree.set_directly_proven(premise);
+        tree.set_implied(tree.root, 1);
+        assert!(!tree.arena[tree.root].groups[1].rejected);
+        tree.set_directly_proven(other);
+        assert!(tree.proof_complete());
+    }
+
+    #[test]
+    fn verifications_are_kept_through_undo() {
+        let (mut tree, premise) = proven_tree();
+        let verdict = crate::ai::Verdict::new(true, "All men are mortal.");
+        let verification = Verification::new(
+            VerificationKind::Direct,
+            "Every man is mortal.",
+            &[],
+            "mock".into(),
+            &verdict,
+        );
+        tree.add_verification(premise, verification.clone()).unwrap();
+        tree.undo().unwrap();
+        assert_eq!(
+            tree.get_verifications(premise).unwrap(),
+            std::slice::from_ref(&verification)
+        );
+
+        let snapshot: TreeState = serde_json::from_value(serde_json::to_value(&tree).unwrap()).unwrap();
+        assert_eq!(snapshot.get_verifications(premise).unwrap(), [verification]);
+    }
+
+    #[test]
+    fn editing_a_premise_makes_implications_stale() {
+        let (mut tree, premise) = proven_tree();
+        let grandparent = tree.add_node("Socrates will die.".into());
+        tree.link(grandparent, tree.root, 0).unwrap();
+        tree.set_implied(grandparent, 0);
+        tree.set_directly_proven(premise);
+        assert!(tree.is_proven(grandparent).unwrap());
+
+        tree.change_node_statement(premise, "Every man is immortal.".into())
+            .unwrap();
+        assert_eq!(
+            tree.arena[tree.root].state,
+            ProofState::None,
+            "the verdict was for the old premise"
+        );
+        assert!(tree.is_stale(&tree.arena[tree.root].groups[0]));
+        assert_eq!(tree.arena[grandparent].state, ProofState::ImpliedUnproven);
+
+        tree.change_node_statement(premise, "every man is  mortal.".into())
+            .unwrap();
+        assert!(
+            !tree.is_stale(&tree.arena[tree.root].groups[0]),
+            "normalized text is unchanged"
+        );
+        assert_eq!(tree.arena[tree.root].state, ProofState::ImpliedUnproven);
+        tree.set_directly_proven(premise);
+        assert!(tree.proof_complete());
+
+        tree.change_node_statement(premise, "Every man is immortal.".into())
+            .unwrap();
+        tree.undo().unwrap();
+        assert!(tree.proof_complete());
+        tree.redo().unwrap();
+        tree.set_implied(tree.root, 0);
+        assert!(!tree.is_stale(&tree.arena[tree.root].groups[0]), "checked again");
+    }
+
+    #[test]
+    fn diffs_contain_derived_changes() {
+        let (mut tree, premise) = proven_tree();
+        let grandparent = tree.add_node("Socrates will die.".into());
+        tree.link(grandparent, tree.root, 0).unwrap();
+        assert!(tree.commit_revision());
+        assert!(!tree.commit_revision(), "nothing changed since");
+        let base = tree.revision();
+        assert_eq!(tree.diff_since(base).unwrap().statements.len(), 0);
+
+        let attacker = tree.add_node("Some men lived forever.".into());
+        tree.attack(attacker, AttackTarget::Node(premise)).unwrap();
+        tree.commit_revision();
+        tree.set_attack_valid(attacker, AttackTarget::Node(premise), true)
+            .unwrap();
+        tree.remove_node(attacker).unwrap();
+        tree.commit_revision();
+        let diff = tree.diff_since(base).unwrap();
+        let changed: HashSet<Index> = diff.statements.iter().map(|node| node.id).collect();
+        assert_eq!(
+            changed,
+            HashSet::from([premise, tree.root, grandparent]),
+            "the grandparent depends on the premise"
+        );
+        assert_eq!(diff.removed, vec![attacker]);
+        assert_eq!(diff.attacks.map(|attacks| attacks.len()), Some(0));
+
+        assert!(tree.diff_since(tree.revision() + 1).is_none());
+        for statement in 0..MAX_REVISIONS {
+            tree.add_node(statement.to_string());
+            tree.commit_revision();
+        }
+        assert!(tree.diff_since(base).is_none(), "too old, clients get the whole tree");
+    }
+
+    #[test]
+    fn verifying_is_counted_and_published() {
+        let (mut tree, premise) = proven_tree();
+        tree.commit_revision();
+        let verifying = |tree: &TreeState| {
+            tree.as_dto()
+                .statements
+                .iter()
+                .find(|node| node.id == premise)
+                .unwrap()
+                .verifying
+        };
+        tree.start_verifying(premise);
+        tree.start_verifying(premise);
+        assert!(tree.commit_revision());
+        assert!(tree
+            .diff_since(tree.revision() - 1)
+            .unwrap()
+            .statements
+            .iter()
+            .any(|node| node.verifying));
+        tree.stop_verifying(premise);
+        assert!(verifying(&tree), "one check is still running");
+        tree.stop_verifying(premise);
+        assert!(!verifying(&tree));
+        assert!(tree.commit_revision());
+        tree.undo().unwrap();
+        assert!(!tree.proof_complete(), "undo skips checks, it reverts the last verdict");
+    }
+
+    #[test]
+    fn any_complete_group_proves() {
+        let (mut tree, premise) = proven_tree();
+        let unproven = tree.add_node("Socrates is a man.".into());
+        tree.link(tree.root, unproven, 0).unwrap();
+        assert!(!tree.proof_complete(), "an unproven premise breaks the group");
+
+        let other = tree.add_node("Socrates drank hemlock.".into());
+        assert!(matches!(
+            tree.link(tree.root, other, 2),
+            Err(ProofError::NoSuchGroup { group: 2, .. })
+        ));
+        tree.link(tree.root, other, 1).unwrap();
+        tree.link(tree.root, premise, 1).unwrap();
+        tree.set_directly_proven(other);
+        assert!(!tree.proof_complete(), "the new group is not accepted yet");
+        tree.set_implied(tree.root, 1);
+        assert!(tree.proof_complete());
+
+        tree.unlink(tree.root, other, Some(1)).unwrap();
+        assert!(
+            !tree.arena[tree.root].groups[1].accepted,
+            "the remaining premise has to be checked again"
+        );
+        assert!(!tree.proof_complete());
+        tree.set_directly_proven(unproven);
+        assert!(tree.proof_complete(), "group 0 is complete");
+
+        let attacker = tree.add_node("Some men lived forever.".into());
+        let link = AttackTarget::Link {
+            premise,
+            conclusion: tree.root,
+        };
+        tree.attack(attacker, link).unwrap();
+        tree.set_attack_valid(attacker, link, true).unwrap();
+        assert_eq!(tree.arena[tree.root].state, ProofState::ImpliedUnproven);
+        tree.unlink(tree.root, premise, Some(1)).unwrap();
+        assert_eq!(tree.attacks.len(), 1, "the premise is still linked in group 0");
+        tree.unlink(tree.root, premise, Some(0)).unwrap();
+        assert!(tree.attacks.is_empty());
+        assert_eq!(tree.arena[tree.root].state, ProofState::None);
+    }
+
+    #[test]
+    fn rejects_attack_cycles() {
+        let (mut tree, premise) = proven_tree();
+        let attacker = tree.add_node("Some men lived forever.".into());
+        tree.attack(attacker, AttackTarget::Node(premise)).unwrap();
+        let Err(ProofError::CircularAttack { cycle, .. }) = tree.attack(premise, AttackTarget::Node(attacker)) else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(cycle, vec![attacker, premise, attacker]);
+        assert!(matches!(
+            tree.attack(premise, AttackTarget::Node(premise)),
+            Err(ProofError::CircularAttack { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_premise_cycles() {
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let root = tree.root;
+        let Err(ProofError::CircularLink { cycle, .. }) = tree.link(root, root, 0) else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(cycle, vec![root, root]);
+
+        let man = tree.add_node("Socrates is a man.".into());
+        tree.link(root, man, 0).unwrap();
+        let Err(ProofError::CircularLink { cycle, .. }) = tree.link(man, root, 0) else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(cycle, vec![man, root, man]);
+
+        let human = tree.add_node("Socrates is human.".into());
+        let born = tree.add_node("Socrates was born.".into());
+        tree.link(man, human, 0).unwrap();
+        tree.link(human, born, 0).unwrap();
+        let Err(ProofError::CircularLink { cycle, .. }) = tree.link(born, root, 0) else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(cycle, vec![born, root, man, human, born]);
+        assert!(tree.get_node(born).unwrap().groups.is_empty());
+    }
+
+    #[test]
+    fn finds_cycles_in_long_chains() {
+        let mut tree = TreeState::new("0".into());
+        let mut last = tree.root;
+        for i in 1..100_000 {
+            let next = tree.add_node(i.to_string());
+            tree.link(last, next, 0).unwrap();
+            last = next;
+        }
+        let Err(ProofError::CircularLink { cycle, .. }) = tree.link(last, tree.root, 0) else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(cycle.len(), 100_001);
+    }
+
+    #[test]
+    fn undo_reverts_verdicts() {
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let premise = tree.add_node("Every man is mortal.".into());
+        tree.link(tree.root, premise, 0).unwrap();
+        tree.set_directly_proven(premise);
+        tree.set_implied(tree.root, 0);
+        assert!(tree.proof_complete());
+
+        tree.undo().unwrap();
+        assert!(!tree.proof_complete());
+        assert!(!tree.arena[tree.root].groups[0].accepted);
+        assert_eq!(tree.arena[premise].state, ProofState::DirectlyProven);
+        tree.undo().unwrap();
+        assert_eq!(tree.arena[premise].state, ProofState::None);
+        tree.redo().unwrap();
+        tree.redo().unwrap();
+        assert!(tree.proof_complete());
+        for _ in 0..4 {
+            tree.undo().unwrap();
         }
+        assert!(tree.arena[premise].removed);
+        assert!(matches!(tree.undo(), Err(ProofError::NothingToUndo)));
+    }
+
+    #[test]
+    fn undoing_an_edit_does_not_keep_the_verdict_on_the_new_text() {
+        let mut tree = TreeState::new("The moon is made of cheese.".into());
+        let root = tree.root;
+        tree.change_node_statement(root, "The moon orbits the earth.".into())
+            .unwrap();
+        tree.set_directly_proven(root);
+        assert!(tree.proof_complete());
+        tree.undo().unwrap();
+        assert_eq!(tree.arena[root].statement, "The moon orbits the earth.");
+        assert_eq!(tree.arena[root].state, ProofState::None);
+        tree.undo().unwrap();
+        assert_eq!(tree.arena[root].statement, "The moon is made of cheese.");
+        assert!(!tree.proof_complete());
+        tree.redo().unwrap();
+        tree.redo().unwrap();
+        assert!(tree.proof_complete());
+
+        let attacker = tree.add_node("The moon is a planet.".into());
+        tree.attack(attacker, AttackTarget::Node(root)).unwrap();
+        tree.change_node_statement(attacker, "The moon is a satellite of the sun.".into())
+            .unwrap();
+        tree.set_attack_valid(attacker, AttackTarget::Node(root), true).unwrap();
+        assert!(!tree.proof_complete());
+        tree.undo().unwrap();
+        tree.undo().unwrap();
+        assert_eq!(tree.arena[attacker].statement, "The moon is a planet.");
+        assert!(!tree.attacks[0].valid, "the attack was judged for the new text");
+        assert!(tree.proof_complete());
+    }
+
+    #[test]
+    fn failed_operations_change_nothing() {
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let conclusion = tree.add_node("Socrates will die.".into());
+        let man = tree.add_node("Socrates is a man.".into());
+        let mortal = tree.add_node("Every man is mortal.".into());
+        tree.link(conclusion, man, 0).unwrap();
+        tree.link(conclusion, mortal, 0).unwrap();
+        // a premise that is gone without being unlinked, so removing the links of `conclusion` fails halfway.
+        tree.arena[mortal].removed = true;
+        assert!(matches!(tree.remove_node(conclusion), Err(ProofError::NoSuchNode(id)) if id == mortal));
+        assert!(!tree.arena[conclusion].removed);
+        assert_eq!(tree.arena[conclusion].groups[0].premises, vec![man, mortal]);
+        assert_eq!(
+            tree.arena[man].parents,
+            vec![conclusion],
+            "the unlink of `man` is reverted"
+        );
+
+        tree.undo().unwrap();
+        assert_eq!(
+            tree.arena[conclusion].groups[0].premises,
+            vec![man],
+            "undo reverts the last link"
+        );
     }
 
-    fn check_if_all_children_true(&mut self, id: Index) -> bool {
-        let parent = self.get_node(id).unwrap();
-        // check all children
-        for &child_id in parent.children.iter() {
-            let child = self.get_node(child_id).unwrap();
-            if !child.is_proven() {
-                return false;
+    #[test]
+    fn shared_premises_are_decided_once() {
+        // two statements per layer, each with both statements of the next layer as premises.
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let mut layer = vec![tree.root];
+        for depth in 0..60 {
+            let next = vec![
+                tree.add_node(format!("{} a", depth)),
+                tree.add_node(format!("{} b", depth)),
+            ];
+            for &conclusion in &layer {
+                for &premise in &next {
+                    tree.link(conclusion, premise, 0).unwrap();
+                }
             }
+            layer = next;
         }
-        true
+        let attacker = tree.add_node("Nothing is certain.".into());
+        tree.attack(attacker, AttackTarget::Node(layer[0])).unwrap();
+        tree.set_attack_valid(attacker, AttackTarget::Node(layer[0]), true)
+            .unwrap();
+        let statements = tree.as_dto().statements;
+        assert_eq!(statements.len(), 122);
+        let unsupported = statements.iter().filter(|node| node.unsupported).count();
+        assert_eq!(unsupported, 119, "all but the last layer and the attacker");
+        assert!(statements.iter().any(|node| node.id == layer[0] && node.defeated));
+    }
+
+    #[test]
+    fn removing_attacker_removes_its_attacks() {
+        let (mut tree, premise) = proven_tree();
+        let attacker = tree.add_node("Some men lived forever.".into());
+        tree.attack(attacker, AttackTarget::Node(premise)).unwrap();
+        tree.set_attack_valid(attacker, AttackTarget::Node(premise), true)
+            .unwrap();
+        tree.remove_node(attacker).unwrap();
+        assert!(tree.proof_complete());
+        tree.undo().unwrap();
+        assert!(!tree.proof_complete());
     }
 }
@@ -0,0 +1,164 @@
+use std::fmt::Display;
+
+use schemars::gen::SchemaSettings;
+use schemars::schema::{RootSchema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::proof::ProofError;
+use super::{ClientMessage, ClientRequest, RequestId, ServerMessage, ServerReply, StatementDTO, TreeStateDTO};
+
+/// version of the websocket messages. Increase it whenever a change breaks existing clients,
+/// and regenerate `protocol.schema.json` with `cargo run -- --schema`.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// the client does not speak the protocol of the server. Sent as `ProtocolError`.
+#[derive(Serialize, JsonSchema, Debug)]
+pub enum ProtocolError {
+    /// the client said `Hello` with another version. The server closes the connection afterwards.
+    UnsupportedVersion { client: u32, server: u32 },
+    /// the message is no `ClientRequest`, e.g. an unknown command, a missing field or an id that is no
+    /// `[index, generation]` pair. Says what is wrong with it.
+    InvalidMessage(String),
+    /// the message is longer than the `message_size` limit of the server, in bytes.
+    MessageTooLarge { size: usize, limit: usize },
+    /// the client sent another request before its `Hello` was accepted. The request is ignored.
+    HelloRequired,
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::UnsupportedVersion { client, server } => write!(
+                f,
+                "This client speaks protocol version {}, the server only version {}. Please reload the page.",
+                client, server
+            ),
+            ProtocolError::InvalidMessage(e) => write!(f, "Invalid message: {}", e),
+            ProtocolError::MessageTooLarge { size, limit } => write!(
+                f,
+                "The message has {} bytes, the server accepts at most {}.",
+                size, limit
+            ),
+            ProtocolError::HelloRequired => write!(f, "Say Hello with the protocol version of this client first."),
+        }
+    }
+}
+
+impl ClientRequest {
+    /// read a message of a client, of at most `max_size` bytes. Envelopes are read apart from their message,
+    /// so errors point at what is wrong with it and keep the request id if there is one.
+    pub fn parse(text: &str, max_size: usize) -> Result<Self, (Option<RequestId>, ProtocolError)> {
+        let invalid = |e: serde_json::Error| ProtocolError::InvalidMessage(e.to_string());
+        if text.len() > max_size {
+            let error = ProtocolError::MessageTooLarge {
+                size: text.len(),
+                limit: max_size,
+            };
+            return Err((None, error));
+        }
+        let message: Value = serde_json::from_str(text).map_err(|e| (None, invalid(e)))?;
+        let Value::Object(mut envelope) = message else {
+            return ClientMessage::deserialize(message)
+                .map(ClientRequest::Bare)
+                .map_err(|e| (None, invalid(e)));
+        };
+        let Some(request_id) = envelope.remove("request_id") else {
+            return ClientMessage::deserialize(Value::Object(envelope))
+                .map(ClientRequest::Bare)
+                .map_err(|e| (None, invalid(e)));
+        };
+        let request_id = RequestId::deserialize(request_id).map_err(|e| (None, invalid(e)))?;
+        let Some(message) = envelope.remove("message") else {
+            let error = ProtocolError::InvalidMessage("missing field `message`".into());
+            return Err((Some(request_id), error));
+        };
+        match ClientMessage::deserialize(message) {
+            Ok(message) => Ok(ClientRequest::Tagged { request_id, message }),
+            Err(e) => Err((Some(request_id), invalid(e))),
+        }
+    }
+}
+
+/// id of a statement, `[index, generation]`. Stands in for `generational_arena::Index` in the schema.
+#[derive(JsonSchema)]
+#[schemars(rename = "Id")]
+#[allow(dead_code)]
+pub struct IndexSchema(usize, u64);
+
+/// JSON Schema of the websocket messages, with one definition for each message type and the types they contain.
+pub fn schema() -> RootSchema {
+    let settings = SchemaSettings::draft07();
+    let meta_schema = settings.meta_schema.clone();
+    let mut generator = settings.into_generator();
+    generator.subschema_for::<ClientRequest>();
+    generator.subschema_for::<ServerMessage>();
+    generator.subschema_for::<ServerReply>();
+    generator.subschema_for::<StatementDTO>();
+    generator.subschema_for::<TreeStateDTO>();
+    generator.subschema_for::<ProofError>();
+    generator.subschema_for::<ProtocolError>();
+    let mut schema = SchemaObject::default();
+    let metadata = schema.metadata();
+    metadata.title = Some("Argue websocket protocol".into());
+    metadata.description = Some(
+        "Clients send a ClientRequest, the server a ServerMessage, or a ServerReply to requests with an id.".into(),
+    );
+    schema
+        .extensions
+        .insert("protocol_version".into(), json!(PROTOCOL_VERSION));
+    RootSchema {
+        meta_schema,
+        schema,
+        definitions: generator.take_definitions(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(text: &str) -> (Option<RequestId>, String) {
+        match ClientRequest::parse(text, 100) {
+            Ok(_) => panic!("{} should be rejected", text),
+            Err((request_id, error)) => (request_id, error.to_string()),
+        }
+    }
+
+    #[test]
+    fn parse_errors_say_what_is_wrong() {
+        assert!(matches!(
+            ClientRequest::parse(r#"{"request_id":"a","message":"Undo"}"#, 100),
+            Ok(ClientRequest::Tagged {
+                message: ClientMessage::Undo,
+                ..
+            })
+        ));
+        assert!(matches!(
+            ClientRequest::parse(r#"{"Delete":{"id":[1,0]}}"#, 100),
+            Ok(ClientRequest::Bare(ClientMessage::Delete { .. }))
+        ));
+
+        let (request_id, error) = parse_error(r#"{"request_id":7,"message":"Fly"}"#);
+        assert_eq!(request_id, Some(RequestId::Number(7)));
+        assert!(error.contains("unknown variant `Fly`"), "{}", error);
+        let (_, error) = parse_error(r#"{"Edit":{"id":[1,0]}}"#);
+        assert!(error.contains("missing field `statement`"), "{}", error);
+        let (_, error) = parse_error(r#"{"Delete":{"id":1}}"#);
+        assert!(error.contains("invalid type: integer `1`"), "{}", error);
+        let (request_id, error) = parse_error(&format!(r#"{{"Add":{{"statement":"{}"}}}}"#, "a".repeat(100)));
+        assert_eq!(request_id, None);
+        assert!(error.starts_with("The message has 124 bytes"), "{}", error);
+    }
+
+    #[test]
+    fn schema_file_is_up_to_date() {
+        let schema = serde_json::to_string_pretty(&schema()).unwrap() + "\n";
+        let file = include_str!("../../protocol.schema.json");
+        assert!(
+            schema == file,
+            "the messages changed, run `cargo run -- --schema > protocol.schema.json`"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use crate::ai::cache::CacheKey;
+use crate::ai::{AiApi, Verdict};
+
+use super::verification::{Verification, VerificationKind};
+
+/// what the AI is asked, with the texts as they were when it was asked.
+/// The verdict only applies to the tree if asking again would be the same question.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Question {
+    Statement(String),
+    Implication {
+        premises: Vec<String>,
+        conclusion: String,
+    },
+    /// `premise` is given for attacks on the inference of `target` from it.
+    Attack {
+        attacker: String,
+        target: String,
+        premise: Option<String>,
+    },
+}
+
+impl Question {
+    fn premises(&self) -> Vec<&str> {
+        match self {
+            Question::Statement(_) => Vec::new(),
+            Question::Implication { premises, .. } => premises.iter().map(String::as_str).collect(),
+            Question::Attack { premise, .. } => premise.as_deref().into_iter().collect(),
+        }
+    }
+
+    /// the statement the verdict is recorded on.
+    fn statement(&self) -> &str {
+        match self {
+            Question::Statement(statement) => statement,
+            Question::Implication { conclusion, .. } => conclusion,
+            Question::Attack { attacker, .. } => attacker,
+        }
+    }
+
+    pub fn cache_key(&self, model: String) -> CacheKey {
+        match self {
+            Question::Statement(statement) => CacheKey::statement(model, statement),
+            Question::Implication { conclusion, .. } => CacheKey::implication(model, &self.premises(), conclusion),
+            Question::Attack {
+                attacker,
+                target,
+                premise,
+            } => CacheKey::attack(model, attacker, target, premise.as_deref()),
+        }
+    }
+
+    pub async fn ask<A: AiApi>(&self, api: &A) -> Result<String, A::AiError> {
+        match self {
+            Question::Statement(statement) => api.check_statement(statement).await,
+            Question::Implication { conclusion, .. } => api.check_implication(&self.premises(), conclusion).await,
+            Question::Attack {
+                attacker,
+                target,
+                premise,
+            } => api.check_attack(attacker, target, premise.as_deref()).await,
+        }
+    }
+
+    pub fn verification(&self, kind: VerificationKind, model: String, verdict: &Verdict) -> Verification {
+        Verification::new(kind, self.statement(), &self.premises(), model, verdict)
+    }
+}
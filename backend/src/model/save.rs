@@ -0,0 +1,203 @@
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::proof::{ProofError, TreeState};
+use crate::storage::StoredComment;
+
+/// version of the documents this build writes. Increase it and add a migration whenever the layout of
+/// `TreeState` changes in a way `#[serde(default)]` cannot cover.
+pub const SAVE_VERSION: u64 = 2;
+
+/// `MIGRATIONS[i]` upgrades a document of version `i + 1` to version `i + 2`.
+const MIGRATIONS: [fn(Value) -> Value; 1] = [from_v1];
+
+/// a complete game as JSON document, kept in the storage and handed out by `/api/games/{id}/save`.
+#[derive(Serialize, Deserialize)]
+pub struct SavedGame<T> {
+    pub version: u64,
+    /// seconds since the unix epoch.
+    pub saved_at: u64,
+    /// statements with their premise groups, proof states and AI verdicts, the attacks and the undo history.
+    pub game: T,
+    /// AI comments, oldest first. Only in downloaded documents, the storage keeps them separately.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<StoredComment>,
+}
+
+impl<'a> SavedGame<&'a TreeState> {
+    pub fn new(game: &'a TreeState, comments: Vec<StoredComment>) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            game,
+            comments,
+        }
+    }
+}
+
+impl SavedGame<TreeState> {
+    /// read a document of this or any older version. The game in it has to be consistent.
+    pub fn load(text: &str) -> Result<Self, LoadError> {
+        let mut document: Value = serde_json::from_str(text).map_err(LoadError::Json)?;
+        // snapshots from before versioning are the bare game.
+        let version = document.get("version").map_or(Some(1), Value::as_u64);
+        let version = version
+            .filter(|version| (1..=SAVE_VERSION).contains(version))
+            .ok_or_else(|| LoadError::UnsupportedVersion(document["version"].clone()))?;
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            document = migrate(document);
+        }
+        let saved: Self = serde_json::from_value(document).map_err(LoadError::Json)?;
+        saved.game.validate().map_err(LoadError::Invalid)?;
+        Ok(saved)
+    }
+}
+
+/// why a saved game could not be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    /// written by a newer build, or no version at all.
+    UnsupportedVersion(Value),
+    /// the game refers to statements, links or premise groups it does not have, its premises are circular,
+    /// or its undo history does not fit it.
+    Invalid(ProofError),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Json(e) => write!(f, "Invalid saved game: {}", e),
+            LoadError::Invalid(e) => write!(f, "Invalid saved game: {}", e),
+            LoadError::UnsupportedVersion(version) => write!(
+                f,
+                "Saved games of version {} are not supported, this server reads versions 1 to {}.",
+                version, SAVE_VERSION
+            ),
+        }
+    }
+}
+
+/// version 1 is the bare game, possibly from before premise groups: the premises of a node are its `children`,
+/// accepted as one group if the node is implied.
+fn from_v1(mut game: Value) -> Value {
+    let nodes = game["arena"].as_array_mut().into_iter().flatten();
+    for node in nodes.filter_map(|entry| entry.get_mut(1)) {
+        let Some(node) = node.as_object_mut() else {
+            continue;
+        };
+        let children = node.remove("children").unwrap_or_default();
+        let has_groups = node
+            .get("groups")
+            .and_then(Value::as_array)
+            .is_some_and(|groups| !groups.is_empty());
+        if !has_groups && children.as_array().is_some_and(|children| !children.is_empty()) {
+            let accepted = matches!(node["state"].as_str(), Some("ImpliedProven" | "ImpliedUnproven"));
+            node.insert("groups".into(), json!([{"premises": children, "accepted": accepted}]));
+        }
+    }
+    json!({"version": 2, "saved_at": 0, "game": game})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proven_tree() -> TreeState {
+        let mut tree = TreeState::new("Socrates is mortal.".into());
+        let root = tree.as_dto().root;
+        let premise = tree.add_node("Every man is mortal.".into());
+        tree.link(root, premise, 0).unwrap();
+        tree.set_directly_proven(premise);
+        tree.set_implied(root, 0);
+        tree
+    }
+
+    #[test]
+    fn loads_what_it_saves() {
+        let tree = proven_tree();
+        let text = serde_json::to_string(&SavedGame::new(&tree, Vec::new())).unwrap();
+        let saved = SavedGame::load(&text).unwrap();
+        assert_eq!(saved.version, SAVE_VERSION);
+        assert_eq!(saved.game.to_argdown(), tree.to_argdown());
+        assert!(saved.game.proof_complete());
+    }
+
+    #[test]
+    fn migrates_snapshots_without_groups() {
+        let tree = proven_tree();
+        let mut snapshot = serde_json::to_value(&tree).unwrap();
+        let root = &mut snapshot["arena"][0][1];
+        let premises = root["groups"][0]["premises"].take();
+        root.as_object_mut().unwrap().remove("groups");
+        root["children"] = premises.clone();
+
+        let saved = SavedGame::load(&snapshot.to_string()).unwrap();
+        let dto = saved.game.as_dto();
+        let root = dto.statements.iter().find(|node| node.id == dto.root).unwrap();
+        assert_eq!(serde_json::to_value(&root.groups[0].premises).unwrap(), premises);
+        assert!(root.groups[0].accepted);
+        assert!(saved.game.proof_complete());
+    }
+
+    #[test]
+    fn rejects_tampered_games() {
+        let tree = proven_tree();
+        let document = serde_json::to_value(SavedGame::new(&tree, Vec::new())).unwrap();
+        let load = |tamper: fn(&mut Value)| {
+            let mut document = document.clone();
+            tamper(&mut document["game"]);
+            SavedGame::load(&document.to_string())
+        };
+        assert!(load(|_| {}).is_ok());
+        let invalid = [
+            load(|game| game["root"] = json!([7, 0])),
+            load(|game| game["arena"][0][1]["groups"][0]["premises"] = json!([[7, 0]])),
+            load(|game| game["arena"][1][1]["parents"] = json!([])),
+            load(|game| game["arena"][0][1]["removed"] = json!(true)),
+            load(|game| game["attacks"] = json!([{"attacker": [7, 0], "target": {"Node": [0, 0]}, "valid": true}])),
+            load(|game| game["history"]["undo"][0][0] = json!({"Remove": [7, 0]})),
+            load(|game| {
+                game["history"]["undo"][0][0] =
+                    json!({"GroupAccepted": {"id": [0, 0], "group": 3, "old": false, "new": true}})
+            }),
+        ];
+        let broken = [
+            load(|game| {
+                let undo = game["history"]["undo"].as_array_mut().unwrap();
+                undo.push(json!([{"AttackValid": {"pos": 9, "old": false, "new": true}}]));
+            }),
+            load(|game| game["history"]["undo"][1][1]["Link"]["child_pos"] = json!(4)),
+            load(|game| game["history"]["redo"] = json!([[{"AttackValid": {"pos": 0, "old": true, "new": false}}]])),
+        ];
+        for result in broken {
+            assert!(
+                matches!(result, Err(LoadError::Invalid(ProofError::BrokenHistory))),
+                "{:?}",
+                result.err()
+            );
+        }
+        for result in invalid {
+            assert!(matches!(result, Err(LoadError::Invalid(_))), "{:?}", result.err());
+        }
+
+        let Err(LoadError::Invalid(ProofError::CircularLink { cycle, .. })) = load(|game| {
+            game["arena"][1][1]["groups"] = json!([{"premises": [[0, 0]], "accepted": false}]);
+            game["arena"][0][1]["parents"] = json!([[1, 0]]);
+        }) else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(cycle.len(), 3);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let text = json!({"version": SAVE_VERSION + 1, "saved_at": 0, "game": {}}).to_string();
+        assert!(matches!(SavedGame::load(&text), Err(LoadError::UnsupportedVersion(_))));
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::proof::AttackTarget;
+use crate::ai::Verdict;
+
+/// what the AI was asked about a statement.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub enum VerificationKind {
+    Direct,
+    Implication {
+        group: usize,
+    },
+    /// the statement attacks `target`, `attacked` is the statement it argued against.
+    Attack {
+        target: AttackTarget,
+        attacked: String,
+    },
+}
+
+/// one verdict of the AI on a statement, kept on the node so players joining later can see why it is in its state.
+/// Verifications are a log: undo does not remove them.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct Verification {
+    pub kind: VerificationKind,
+    /// the statement as it was asked, it might have been edited since.
+    pub statement: String,
+    /// premises shown to the AI, for attacks on an inference the attacked premise.
+    pub premises: Vec<String>,
+    /// the model, or models of a consensus, that gave the verdict.
+    pub model: String,
+    pub verdict: bool,
+    pub explanation: String,
+    /// answered from the verdict cache.
+    pub cached: bool,
+    /// seconds since the unix epoch.
+    pub timestamp: u64,
+}
+
+impl Verification {
+    pub fn new(kind: VerificationKind, statement: &str, premises: &[&str], model: String, verdict: &Verdict) -> Self {
+        Self {
+            kind,
+            statement: statement.to_string(),
+            premises: premises.iter().map(|premise| premise.to_string()).collect(),
+            model,
+            verdict: verdict.verdict,
+            explanation: verdict.explanation.clone(),
+            cached: verdict.cached,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+        }
+    }
+}
@@ -1 +1,86 @@
-pub async fn create_game() -> &'static str { "asdf" }
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::games::{GameId, Games};
+use crate::model::{schema, ExportFormat, ImportFormat};
+
+#[derive(Deserialize)]
+pub struct CreateGameRequest {
+    statement: String,
+}
+
+#[derive(Serialize)]
+pub struct CreateGameResponse {
+    id: GameId,
+}
+
+pub async fn create_game(State(games): State<Arc<Games>>, Json(request): Json<CreateGameRequest>) -> Response {
+    match games.create(request.statement).await {
+        Ok(id) => Json(CreateGameResponse { id }).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    format: ImportFormat,
+}
+
+/// create a game from an argument map in the request body, its first statement becomes the root.
+pub async fn import_game(Query(query): Query<ImportQuery>, State(games): State<Arc<Games>>, body: String) -> Response {
+    match games.import(query.format, &body).await {
+        Ok(id) => Json(CreateGameResponse { id }).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: ExportFormat,
+}
+
+/// the game as GraphViz DOT, Mermaid flowchart or Markdown outline.
+pub async fn export_game(
+    Path(game_id): Path<GameId>,
+    Query(query): Query<ExportQuery>,
+    State(games): State<Arc<Games>>,
+) -> Response {
+    let Some(game) = games.get(game_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let export = game.lock().await.export(query.format);
+    ([(header::CONTENT_TYPE, query.format.content_type())], export).into_response()
+}
+
+/// the complete game as versioned JSON document, see `SavedGame`.
+pub async fn save_game(Path(game_id): Path<GameId>, State(games): State<Arc<Games>>) -> Response {
+    let Some(game) = games.get(game_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let saved = game.lock().await.saved();
+    let attachment = format!("attachment; filename=\"argue-{}.json\"", game_id);
+    (
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, attachment),
+        ],
+        saved,
+    )
+        .into_response()
+}
+
+/// create a game from a document of `save_game`, also of older versions.
+pub async fn load_game(State(games): State<Arc<Games>>, body: String) -> Response {
+    match games.load_saved(&body).await {
+        Ok(id) => Json(CreateGameResponse { id }).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// JSON Schema of the websocket messages, for clients to generate their types from.
+pub async fn protocol_schema() -> Json<schemars::schema::RootSchema> { Json(schema()) }
@@ -1,30 +1,65 @@
+use std::sync::Arc;
+
 use axum::extract::ws::{Message, WebSocket};
-use axum::extract::WebSocketUpgrade;
-use axum::response::Response;
+use axum::extract::{Path, State, WebSocketUpgrade};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use futures_util::stream::StreamExt;
+use futures_util::SinkExt;
+use tokio::sync::{mpsc, Mutex};
 
-use crate::model::{ClientMessage, GameState, Messenger};
+use crate::games::{GameId, Games};
+use crate::model::{ClientRequest, GameState};
 
-pub async fn ws_route_handler(ws: WebSocketUpgrade) -> Response { ws.on_upgrade(|socket| handle_socket(socket)) }
+pub async fn ws_route_handler(
+    ws: WebSocketUpgrade,
+    Path(game_id): Path<GameId>,
+    State(games): State<Arc<Games>>,
+) -> Response {
+    let Some(game) = games.get(game_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    // messages a bit over the limit are still read, so they can be answered with `MessageTooLarge`.
+    // Anything larger ends the connection before it is buffered.
+    let max_size = games.limits().message_size.saturating_mul(2);
+    ws.max_message_size(max_size)
+        .max_frame_size(max_size)
+        .on_upgrade(|socket| handle_socket(socket, game))
+}
 
 /// one method call = one websocket connection
-async fn handle_socket(socket: WebSocket) {
-    let (sender, mut receiver) = socket.split();
+async fn handle_socket(socket: WebSocket, game: Arc<Mutex<GameState>>) {
+    let (mut sender, mut receiver) = socket.split();
 
-    //gamestate
-    let messenger = Messenger { sender };
-    let mut game_state = GameState::new("".to_string(), messenger);
+    // the game pushes messages for this client into the channel, this task forwards them to the socket.
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let send_task = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if sender.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let client = game.lock().await.connect(tx);
     while let Some(result) = receiver.next().await {
         let msg = match result {
             Ok(Message::Text(msg)) => msg,
             Ok(Message::Binary(_) | Message::Ping(_) | Message::Pong(_)) => continue,
-            Ok(Message::Close(_)) | Err(_) => return,
+            Ok(Message::Close(_)) | Err(_) => break,
         };
 
-        let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&msg) else {
-            eprintln!("received invalid message: {}", msg);
-            continue;
-        };
-        game_state.on_incoming_message(client_msg).await;
+        let mut game = game.lock().await;
+        match ClientRequest::parse(&msg, game.limits().message_size) {
+            Ok(request) => game.on_incoming_message(client, request),
+            Err((request_id, error)) => game.reject(client, request_id, error),
+        }
+        if !game.connected(client) {
+            break;
+        }
     }
+    // dropping the sender of the client ends the send task once it forwarded everything still queued,
+    // e.g. a final error or close frame.
+    game.lock().await.disconnect(client);
+    let _ = send_task.await;
 }
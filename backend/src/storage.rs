@@ -0,0 +1,245 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+use generational_arena::Index;
+use rusqlite::types::Type;
+use rusqlite::{ffi, params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::ai::cache::CacheKey;
+use crate::ai::Verdict;
+use crate::games::GameId;
+
+/// SQLite file holding snapshots of all games, so they survive disconnects and server restarts.
+/// The connection lives in a thread of its own, so writes never block the game that triggered them.
+/// Reads wait for all writes queued before them, without blocking the thread that awaits them.
+pub struct Storage {
+    jobs: mpsc::Sender<Job>,
+}
+
+type Job = Box<dyn FnOnce(&Connection) + Send>;
+
+/// an AI comment on a single node, as sent in `ServerMessage::Comment`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredComment {
+    pub id: Index,
+    pub comment: String,
+    pub success: bool,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS games (
+    id TEXT PRIMARY KEY,
+    tree TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS comments (
+    game_id TEXT NOT NULL REFERENCES games(id),
+    node_index INTEGER NOT NULL,
+    node_generation INTEGER NOT NULL,
+    comment TEXT NOT NULL,
+    success INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS comments_game ON comments(game_id);
+CREATE TABLE IF NOT EXISTS verdicts (
+    key TEXT PRIMARY KEY,
+    verdict TEXT NOT NULL
+);
+";
+
+impl Storage {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> { Self::start(Connection::open(path)?) }
+
+    /// a database that is gone once the storage is dropped.
+    #[cfg(test)]
+    pub fn in_memory() -> rusqlite::Result<Self> { Self::start(Connection::open_in_memory()?) }
+
+    fn start(connection: Connection) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in queue {
+                job(&connection);
+            }
+        });
+        Ok(Self { jobs })
+    }
+
+    /// run `query` after all queued writes and wait for its result.
+    async fn read<T: Send + 'static>(
+        &self,
+        query: impl FnOnce(&Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> rusqlite::Result<T> {
+        let (result, receiver) = oneshot::channel();
+        let job: Job = Box::new(move |connection| {
+            let _ = result.send(query(connection));
+        });
+        self.jobs.send(job).map_err(|_| stopped())?;
+        receiver.await.map_err(|_| stopped())?
+    }
+
+    /// queue `statement` without waiting for it. Writes run in the order they are queued,
+    /// failures are only logged as "could not `what`".
+    fn write(&self, what: String, statement: impl FnOnce(&Connection) -> rusqlite::Result<usize> + Send + 'static) {
+        let failed = format!("could not {}", what);
+        let job: Job = Box::new(move |connection| {
+            if let Err(e) = statement(connection) {
+                tracing::error!("could not {}: {}", what, e);
+            }
+        });
+        if self.jobs.send(job).is_err() {
+            tracing::error!("{}: {}", failed, stopped());
+        }
+    }
+
+    /// insert or replace the snapshot of a game.
+    pub fn save_tree<T: Serialize>(&self, game: GameId, tree: &T) {
+        let what = format!("save game {}", game);
+        let tree = match serde_json::to_string(tree) {
+            Ok(tree) => tree,
+            Err(e) => return tracing::error!("could not {}: {}", what, e),
+        };
+        self.write(what, move |connection| {
+            connection.execute(
+                "INSERT INTO games (id, tree) VALUES (?1, ?2) ON CONFLICT(id) DO UPDATE SET tree = excluded.tree",
+                params![game.to_string(), tree],
+            )
+        });
+    }
+
+    /// latest snapshot of a game as JSON, if it was ever saved.
+    pub async fn load_tree(&self, game: GameId) -> rusqlite::Result<Option<String>> {
+        self.read(move |connection| {
+            connection
+                .query_row(
+                    "SELECT tree FROM games WHERE id = ?1",
+                    params![game.to_string()],
+                    |row| row.get(0),
+                )
+                .optional()
+        })
+        .await
+    }
+
+    pub fn add_comment(&self, game: GameId, comment: &StoredComment) {
+        let (index, generation) = comment.id.into_raw_parts();
+        let (text, success) = (comment.comment.clone(), comment.success);
+        self.write(format!("store comment in game {}", game), move |connection| {
+            connection.execute(
+                "INSERT INTO comments (game_id, node_index, node_generation, comment, success) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![game.to_string(), index, generation, text, success],
+            )
+        });
+    }
+
+    /// all comments of a game, oldest first.
+    pub async fn comments(&self, game: GameId) -> rusqlite::Result<Vec<StoredComment>> {
+        self.read(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT node_index, node_generation, comment, success FROM comments WHERE game_id = ?1 ORDER BY rowid",
+            )?;
+            let comments = statement
+                .query_map(params![game.to_string()], |row| {
+                    Ok(StoredComment {
+                        id: Index::from_raw_parts(row.get(0)?, row.get(1)?),
+                        comment: row.get(2)?,
+                        success: row.get(3)?,
+                    })
+                })?
+                .collect();
+            comments
+        })
+        .await
+    }
+
+    /// the verdict a model gave on the same question before, shared by all games.
+    pub async fn cached_verdict(&self, key: &CacheKey) -> rusqlite::Result<Option<Verdict>> {
+        let key = key.to_string();
+        self.read(move |connection| {
+            let verdict: Option<String> = connection
+                .query_row("SELECT verdict FROM verdicts WHERE key = ?1", params![key], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            verdict
+                .map(|verdict| serde_json::from_str(&verdict))
+                .transpose()
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+        })
+        .await
+    }
+
+    /// insert or replace the cached verdict for a question.
+    pub fn cache_verdict(&self, key: &CacheKey, verdict: &Verdict) {
+        let verdict = match serde_json::to_string(verdict) {
+            Ok(verdict) => verdict,
+            Err(e) => return tracing::error!("could not cache verdict: {}", e),
+        };
+        let key = key.to_string();
+        self.write("cache verdict".into(), move |connection| {
+            connection.execute(
+                "INSERT INTO verdicts (key, verdict) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET verdict = excluded.verdict",
+                params![key, verdict],
+            )
+        });
+    }
+}
+
+/// error of all requests once the thread of the connection is gone.
+fn stopped() -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        ffi::Error::new(ffi::SQLITE_ABORT),
+        Some("the storage thread stopped".into()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn restores_what_was_saved() {
+        let storage = Storage::in_memory().unwrap();
+        let game = GameId::new();
+        assert_eq!(storage.load_tree(game).await.unwrap(), None);
+
+        storage.save_tree(game, &json!({"root": [0, 0], "revision": 1}));
+        storage.save_tree(game, &json!({"root": [0, 0], "revision": 2}));
+        let comment = StoredComment {
+            id: Index::from_raw_parts(1, 0),
+            comment: "Classic syllogism.".into(),
+            success: true,
+        };
+        storage.add_comment(game, &comment);
+
+        let tree: Value = serde_json::from_str(&storage.load_tree(game).await.unwrap().unwrap()).unwrap();
+        assert_eq!(
+            tree,
+            json!({"root": [0, 0], "revision": 2}),
+            "later snapshots replace earlier ones"
+        );
+        let comments = storage.comments(game).await.unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].id, comment.id);
+        assert_eq!(comments[0].comment, comment.comment);
+        assert!(storage.comments(GameId::new()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unreadable_verdicts_are_errors() {
+        let storage = Storage::in_memory().unwrap();
+        let key = CacheKey::statement("m".into(), "Socrates is mortal.");
+        assert!(storage.cached_verdict(&key).await.unwrap().is_none());
+        storage.cache_verdict(&key, &Verdict::new(true, "Socrates is a man."));
+        assert!(storage.cached_verdict(&key).await.unwrap().unwrap().verdict);
+
+        let raw_key = key.to_string();
+        storage.write("break the cache".into(), move |connection| {
+            connection.execute("UPDATE verdicts SET verdict = '{' WHERE key = ?1", params![raw_key])
+        });
+        assert!(storage.cached_verdict(&key).await.is_err());
+    }
+}
@@ -0,0 +1,11 @@
+import axios from "axios";
+
+/**
+ * Creates a new game with `statement` as root statement and returns its id.
+ */
+export async function createGame(statement: string): Promise<string> {
+  const response = await axios.post<{ id: string }>("/api/create", {
+    statement,
+  });
+  return response.data.id;
+}
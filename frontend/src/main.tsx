@@ -2,6 +2,7 @@ import React from "react";
 import ReactDOM from "react-dom/client";
 import { RouterProvider, createBrowserRouter } from "react-router-dom";
 import { LandingPage } from "pages/landing";
+import { GamePage } from "pages/game";
 
 // import css file, so that tailwind knows what we need
 import "index.css";
@@ -11,6 +12,11 @@ const router = createBrowserRouter([
     path: "/",
     element: <LandingPage />,
   },
+  {
+    // the id returned by `/api/create`, the game is joined at `/ws/{id}`.
+    path: "/game/:id",
+    element: <GamePage />,
+  },
 ]);
 
 ReactDOM.createRoot(document.getElementById("root")!).render(
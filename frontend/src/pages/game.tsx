@@ -1,3 +1,3 @@
 export function GamePage() {
-  <div>Lets Game</div>;
+  return <div>Lets Game</div>;
 }
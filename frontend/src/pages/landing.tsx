@@ -1,12 +1,16 @@
 import { ArrowTopRightOnSquareIcon } from "@heroicons/react/24/outline";
 import { FormEvent } from "react";
-
-const onSubmit = (e: FormEvent<HTMLFormElement>) => {
-  e.preventDefault();
-  const formData = new FormData(e.currentTarget);
-};
+import { useNavigate } from "react-router-dom";
+import { createGame } from "lib/fetch";
 
 export function LandingPage() {
+  const navigate = useNavigate();
+  const onSubmit = async (e: FormEvent<HTMLFormElement>) => {
+    e.preventDefault();
+    const formData = new FormData(e.currentTarget);
+    const id = await createGame(formData.get("text") as string);
+    navigate(`/game/${id}`);
+  };
   const items = ["God is dead.", "The earth is flat."];
   return (
     <div className="inline-flex flex-col items-center justify-center bg-rosePine-base w-dvw h-dvh text-rosePine-text">